@@ -1,15 +1,30 @@
+use std::sync::MutexGuard;
+
 use bitfield_struct::bitfield;
 use libc::pthread_spinlock_t;
 
 use crate::hyperion::components::context::{EmbeddedTraversalContext, OperationContext};
-use crate::hyperion::components::jump_table::{SubNodeJumpTable, SubNodeJumpTableEntry, TOPLEVEL_JUMPTABLE_ENTRIES};
+use crate::hyperion::components::error::HyperionError;
+use crate::hyperion::components::jump_table::{JumpSlot,
+                                              SubNodeJumpTable,
+                                              SubNodeJumpTableEntry,
+                                              SUBLEVEL_JUMPTABLE_ENTRIES,
+                                              TOPLEVEL_JUMPTABLE_ENTRIES};
+use crate::hyperion::components::node::{NodeType, NodeValue};
+use crate::hyperion::components::node_header::NodeHeader;
 use crate::hyperion::internals::atomic_pointer::AtomicArena;
-use crate::hyperion::internals::core::GLOBAL_CONFIG;
-use crate::memorymanager::api::HyperionPointer;
+use crate::hyperion::internals::core::{global_config, EmbeddingPolicy, FreeSpacePolicy, GlobalConfiguration, GrowthPolicy};
+use crate::memorymanager::api::{get_pointer, reallocate, Arena, HyperionPointer};
 
 pub const CONTAINER_MAX_EMBEDDED_DEPTH: usize = 28;
+pub const CONTAINER_MAX_EMBEDDED_SIZE: usize = u8::MAX as usize;
+pub const CONTAINER_MAX_JUMP_TABLES: u8 = 7;
+/// Largest size in bytes the 19 bit size field of a container header can describe.
+pub const CONTAINER_MAX_SIZE: u32 = (1 << 19) - 1;
+/// Largest number of trailing free bytes the 8 bit free bytes field of a container header can describe.
+pub const CONTAINER_MAX_FREESIZE: u32 = u8::MAX as u32;
 
-#[bitfield(u32, order = Msb)]
+#[bitfield(u32, order = Msb, repr = u32, from = u32::from_le, into = u32::to_le)]
 pub struct Container {
     #[bits(19)]
     pub size: u32,
@@ -40,7 +55,27 @@ impl Container {
 
     pub fn get_jump_table_entry_mut(&mut self) -> &mut SubNodeJumpTableEntry {
         let container_pointer: *mut Container = self as *mut Self;
-        unsafe { (container_pointer.add(1) as *mut SubNodeJumpTableEntry).as_mut().unwrap() }
+        unsafe { &mut *(container_pointer.add(1) as *mut SubNodeJumpTableEntry) }
+    }
+
+    /// Returns the number of jump table blocks a container holding `top_node_count` top nodes should carry.
+    ///
+    /// Containers are only ever promoted, so the result never drops below the currently allocated jump table.
+    pub fn get_target_jump_table(&self, top_node_count: u32) -> u8 {
+        let threshold: u32 = global_config().container_jump_table_threshold;
+        if threshold == 0 {
+            return self.jump_table();
+        }
+        let target: u8 = (top_node_count / threshold).min(CONTAINER_MAX_JUMP_TABLES as u32) as u8;
+        target.max(self.jump_table())
+    }
+
+    /// Checks if the jump table of this container should grow for `top_node_count` top nodes.
+    ///
+    /// Returns `true`, if the promotion threshold for another jump table block was crossed.
+    /// Returns `false`, otherwise.
+    pub fn requires_jump_table_promotion(&self, top_node_count: u32) -> bool {
+        self.get_target_jump_table(top_node_count) > self.jump_table()
     }
 
     pub fn get_container_head_size(&self) -> i32 {
@@ -55,23 +90,107 @@ impl Container {
         self.set_free_bytes(size_left as u8);
     }
 
-    pub fn increment_container_size(&mut self, required_minimum: i32) -> u32 {
-        let container_increment: u8 = unsafe { GLOBAL_CONFIG.lock().unwrap().header.container_size_increment() };
+    /// Shrinks this container so that at most `max_trailing_free` trailing free bytes remain, in multiples of the configured increment.
+    ///
+    /// Only updates the header, the caller reallocates the chunk holding the container to the new size.
+    /// Returns the number of bytes released.
+    pub fn trim_free_space(&mut self, max_trailing_free: u32) -> u32 {
+        let container_increment: u32 = global_config().header.container_size_increment() as u32;
+        let excess: u32 = (self.free_bytes() as u32).saturating_sub(max_trailing_free.min(CONTAINER_MAX_FREESIZE));
+        let released: u32 = excess - excess % container_increment;
+        self.set_size(self.size() - released);
+        self.set_free_bytes(self.free_bytes() - released as u8);
+        released
+    }
+
+    /// Trims this container after an embedded container was ejected from it, if the free space policy asks for it.
+    ///
+    /// Returns the number of bytes released.
+    pub fn trim_after_eject(&mut self) -> u32 {
+        let policy: FreeSpacePolicy = global_config().free_space_policy;
+        if !policy.trim_on_eject {
+            return 0;
+        }
+        self.trim_free_space(policy.max_trailing_free)
+    }
+
+    /// Trims this container after a key was deleted from it, if the free space policy asks for it.
+    ///
+    /// Returns the number of bytes released.
+    pub fn trim_after_delete(&mut self) -> u32 {
+        let policy: FreeSpacePolicy = global_config().free_space_policy;
+        if !policy.trim_on_delete {
+            return 0;
+        }
+        self.trim_free_space(policy.max_trailing_free)
+    }
+
+    /// Returns the size this container grows to, if it has to provide `required_minimum` additional bytes.
+    ///
+    /// The configured `GrowthPolicy` may grow the container further, but never beyond the size cap.
+    fn get_incremented_size(&self, required_minimum: i32) -> u32 {
+        let config: MutexGuard<GlobalConfiguration> = global_config();
+        let container_increment: u8 = config.header.container_size_increment();
         let mut factor: i32 = required_minimum / container_increment as i32;
         if required_minimum % container_increment as i32 != 0 {
             factor += 1;
         }
-        self.set_size(self.size() + factor as u32 * container_increment as u32);
-        self.size()
+        let minimum_size: u32 = self.size() + factor as u32 * container_increment as u32;
+
+        let policy_size: u32 = match config.container_growth_policy {
+            GrowthPolicy::Linear => minimum_size,
+            GrowthPolicy::Exponential { percent } => {
+                let growth: u32 = (self.size() as u64 * percent as u64 / 100) as u32;
+                self.size() + growth.div_ceil(container_increment as u32) * container_increment as u32
+            },
+            GrowthPolicy::Custom(growth_fn) => growth_fn(self.size(), required_minimum as u32, container_increment as u32)
+        };
+        policy_size.min(config.container_size_cap.min(CONTAINER_MAX_SIZE)).max(minimum_size)
+    }
+
+    /// Returns the effective size cap of containers, i.e. the configured cap bounded by what the container header can describe.
+    pub fn get_size_cap() -> u32 {
+        global_config().container_size_cap.min(CONTAINER_MAX_SIZE)
+    }
+
+    /// Checks if providing `required_minimum` additional bytes would grow this container beyond the size cap.
+    ///
+    /// Returns `true`, if the write has to split the container instead of growing it.
+    /// Returns `false`, otherwise.
+    pub fn requires_split(&self, required_minimum: i32) -> bool {
+        self.get_incremented_size(required_minimum) > Container::get_size_cap()
+    }
+
+    /// Checks if this container can be split, which requires at least two top nodes to distribute.
+    ///
+    /// Returns `true`, if the container holds at least two top nodes.
+    /// Returns `false`, otherwise.
+    pub fn can_split(&self) -> bool {
+        self.top_node_keys().nth(1).is_some()
+    }
+
+    /// Grows this container by the smallest multiple of the configured increment that provides `required_minimum` additional bytes.
+    ///
+    /// Returns the new size of the container.
+    /// Returns `ContainerSizeExceeded`, if the container would grow beyond the size cap. Writes are expected to check
+    /// [`Container::requires_split`] beforehand, so this error is only surfaced if the container cannot be split.
+    pub fn increment_container_size(&mut self, required_minimum: i32) -> Result<u32, HyperionError> {
+        let size: u32 = self.get_incremented_size(required_minimum);
+        let limit: u32 = Container::get_size_cap();
+        if size > limit {
+            return Err(HyperionError::ContainerSizeExceeded { size, limit });
+        }
+        self.set_size(size);
+        Ok(size)
     }
 
-    pub fn update_top_node_jumptable_entries(&mut self, operation_context: &mut OperationContext, usage_delta: i16) {
+    pub fn update_top_node_jumptable_entries(&mut self, operation_context: &mut OperationContext, usage_delta: i16) -> Result<(), HyperionError> {
         if self.jump_table() == 0 {
-            return;
+            return Ok(());
         }
-        let top_node_key = operation_context.jump_context.as_mut().unwrap().top_node_key;
-        let embedded_context: &mut Option<EmbeddedTraversalContext> = &mut operation_context.embedded_traversal_context;
-        let root_container: &mut Container = embedded_context.as_mut().unwrap().root_container;
+        let top_node_key = operation_context.get_jump_context_mut()?.top_node_key;
+        let embedded_context: &mut EmbeddedTraversalContext = operation_context.get_embedded_traversal_context_mut()?;
+        let root_container: &mut Container = embedded_context.root_container;
 
         let items = TOPLEVEL_JUMPTABLE_ENTRIES * self.jump_table() as usize;
         let jump_table_entry_base: *mut SubNodeJumpTableEntry = root_container.get_jump_table_pointer();
@@ -85,10 +204,234 @@ impl Container {
                     let current_offset: u32 = (*jump_table_entry).offset();
                     (*jump_table_entry).set_offset(current_offset + usage_delta as u32);
                 } else {
-                    return;
+                    return Ok(());
                 }
             }
         }
+        Ok(())
+    }
+
+    /// Calls `visit` with the offset and header of every node stored in this container, in layout order.
+    ///
+    /// Returns the offset behind the last node.
+    /// Returns `Corruption`, if a node exceeds the used bytes of the container.
+    fn scan_nodes(&self, mut visit: impl FnMut(usize, &NodeHeader)) -> Result<usize, HyperionError> {
+        let base: *const u8 = self as *const Container as *const u8;
+        let end: usize = self.size() as usize - self.free_bytes() as usize;
+        let mut offset: usize = (self.get_container_head_size() + self.get_jump_table_size()) as usize;
+
+        while offset < end {
+            let node: &NodeHeader = unsafe { &*(base.add(offset) as *const NodeHeader) };
+            if node.as_top_node().type_flag() == NodeType::Invalid {
+                break;
+            }
+            visit(offset, node);
+            offset += node.get_offset_to_next_node();
+        }
+        if offset > end {
+            return Err(HyperionError::Corruption("node exceeds the used bytes of the container"));
+        }
+        Ok(offset)
+    }
+
+    /// Counts the nodes of this container storing their key as a delta to the preceding node versus as an explicit key byte.
+    ///
+    /// Returns `Corruption`, if a node exceeds the used bytes of the container.
+    pub fn key_encoding_stats(&self) -> Result<KeyEncodingStats, HyperionError> {
+        let mut stats: KeyEncodingStats = KeyEncodingStats::default();
+        let first_node: usize = (self.get_container_head_size() + self.get_jump_table_size()) as usize;
+        let end: usize = self.scan_nodes(|_, node: &NodeHeader| match (node.as_top_node().is_top_node(), node.as_top_node().has_delta()) {
+            (true, true) => stats.top_nodes_delta += 1,
+            (true, false) => stats.top_nodes_explicit += 1,
+            (false, true) => stats.sub_nodes_delta += 1,
+            (false, false) => stats.sub_nodes_explicit += 1
+        })?;
+        stats.scanned_bytes = (end - first_node) as u32;
+        Ok(stats)
+    }
+
+    /// Discards and recomputes the jump successor of every top node in this container from the node layout, e.g. after repair or migration.
+    ///
+    /// Each jump successor is set to the distance to the next top node, or to the end of the used bytes for the last top node, so that
+    /// every rebuilt jump lands on a node boundary.
+    /// Returns the number of rebuilt jump successors.
+    /// Returns `Corruption`, if a node exceeds the used bytes of the container or a distance does not fit into its jump slot.
+    pub fn rebuild_jump_successors(&mut self) -> Result<usize, HyperionError> {
+        let base: *mut u8 = self as *mut Container as *mut u8;
+        let size: usize = self.size() as usize;
+        let mut top_node_offsets: Vec<usize> = vec![];
+        let offset: usize = self.scan_nodes(|node_offset: usize, node: &NodeHeader| {
+            if node.as_top_node().is_top_node() {
+                top_node_offsets.push(node_offset);
+            }
+        })?;
+
+        let mut rebuilt: usize = 0;
+        for (i, node_offset) in top_node_offsets.iter().enumerate() {
+            let node: &mut NodeHeader = unsafe { &mut *(base.add(*node_offset) as *mut NodeHeader) };
+            if node.as_top_node().jump_successor() == 0 {
+                continue;
+            }
+            let target_offset: usize = top_node_offsets.get(i + 1).copied().unwrap_or(offset);
+            let mut slot: JumpSlot = node.jump_slot();
+            let distance: usize = target_offset - node_offset;
+            if distance > u16::MAX as usize || !slot.is_valid_jump(distance as u16, *node_offset, size) {
+                return Err(HyperionError::Corruption("jump successor does not fit between its top node and the successor"));
+            }
+            slot.set_target(*node_offset, target_offset, size);
+            rebuilt += 1;
+        }
+        Ok(rebuilt)
+    }
+
+    /// Removes the value of the leaf starting at `node_offset`, which turns it into a leaf without value, and moves the following nodes
+    /// over the freed value slot.
+    ///
+    /// Top-level jump table entries and sub-node jump table entries of the owning top node behind the node are moved along, jump
+    /// successors are rebuilt and the container is trimmed, if the free space policy asks for it after deletes or the free bytes exceed
+    /// `CONTAINER_MAX_FREESIZE`. The caller shrinks the chunk holding the container by the released bytes.
+    /// Returns the number of bytes released.
+    /// Returns `Corruption`, if the node or its value exceeds the used bytes of the container, or if `node_offset` is not the start of a
+    /// node of this container. Nodes of embedded containers are rejected as well, since the node scan steps over embedded containers.
+    pub fn remove_node_value(&mut self, node_offset: usize) -> Result<u32, HyperionError> {
+        let base: *mut u8 = self as *mut Container as *mut u8;
+        let end: usize = self.size() as usize - self.free_bytes() as usize;
+        if node_offset >= end {
+            return Err(HyperionError::Corruption("node exceeds the used bytes of the container"));
+        }
+
+        let mut top_node_offset: Option<usize> = None;
+        let mut is_node_start: bool = false;
+        self.scan_nodes(|offset: usize, node: &NodeHeader| {
+            is_node_start |= offset == node_offset;
+            if offset <= node_offset && node.as_top_node().is_top_node() {
+                top_node_offset = Some(offset);
+            }
+        })?;
+        if !is_node_start {
+            return Err(HyperionError::Corruption("offset does not start a node of the container"));
+        }
+
+        let node: &mut NodeHeader = unsafe { &mut *(base.add(node_offset) as *mut NodeHeader) };
+        if node.as_top_node().type_flag() != NodeType::LeafNodeWithValue {
+            return Ok(0);
+        }
+        let value_start: usize = node_offset + node.get_offset_node_value();
+        let value_end: usize = value_start + size_of::<NodeValue>();
+        if value_end > end {
+            return Err(HyperionError::Corruption("node value exceeds the used bytes of the container"));
+        }
+        node.as_top_node_mut().set_type_flag(NodeType::LeafNodeEmpty);
+
+        let bytes: &mut [u8] = unsafe { std::slice::from_raw_parts_mut(base, end) };
+        bytes.copy_within(value_end..end, value_start);
+        bytes[end - size_of::<NodeValue>()..].fill(0);
+
+        let jump_table_entries: *mut SubNodeJumpTableEntry = self.get_jump_table_pointer();
+        for i in 0..self.get_jump_table_entry_count() as usize {
+            let entry: &mut SubNodeJumpTableEntry = unsafe { &mut *jump_table_entries.add(i) };
+            if entry.offset() as usize > node_offset {
+                entry.set_offset(entry.offset() - size_of::<NodeValue>() as u32);
+            }
+        }
+        if let Some(top_node_offset) = top_node_offset {
+            self.shift_top_node_jump_table(top_node_offset, node_offset);
+        }
+
+        let container_increment: u32 = global_config().header.container_size_increment() as u32;
+        let free_bytes: u32 = self.free_bytes() as u32 + size_of::<NodeValue>() as u32;
+        let released: u32 = (free_bytes.saturating_sub(CONTAINER_MAX_FREESIZE)).div_ceil(container_increment) * container_increment;
+        self.set_size(self.size() - released);
+        self.set_free_bytes((free_bytes - released) as u8);
+
+        self.rebuild_jump_successors()?;
+        Ok(released + self.trim_after_delete())
+    }
+
+    /// Moves the sub-node jump table entries of the top node at `top_node_offset` pointing behind `node_offset` along with the value slot
+    /// removed from that node.
+    fn shift_top_node_jump_table(&mut self, top_node_offset: usize, node_offset: usize) {
+        let base: *mut u8 = self as *mut Container as *mut u8;
+        let top_node: &NodeHeader = unsafe { &*(base.add(top_node_offset) as *const NodeHeader) };
+        if top_node.as_top_node().jump_table() == 0 {
+            return;
+        }
+        let jump_table: *mut u16 = unsafe { base.add(top_node_offset + top_node.get_offset_jump_table() as usize) as *mut u16 };
+        for i in 0..SUBLEVEL_JUMPTABLE_ENTRIES {
+            let entry: *mut u16 = unsafe { jump_table.add(i) };
+            let jump: u16 = u16::from_le(unsafe { entry.read_unaligned() });
+            if jump != 0 && top_node_offset + jump as usize > node_offset {
+                unsafe { entry.write_unaligned((jump - size_of::<NodeValue>() as u16).to_le()) };
+            }
+        }
+    }
+
+    /// Returns an iterator over the distinct next bytes stored in this container, i.e. the keys of its top nodes, without descending into
+    /// child containers.
+    pub fn top_node_keys(&self) -> TopNodeKeys<'_> {
+        TopNodeKeys {
+            container: self,
+            offset: (self.get_container_head_size() + self.get_jump_table_size()) as usize,
+            end: self.size() as usize - self.free_bytes() as usize,
+            last_key: None
+        }
+    }
+}
+
+/// Breakdown of the key encodings used by the nodes of a container, see [`Container::key_encoding_stats`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct KeyEncodingStats {
+    /// Top nodes storing their key as a delta to the preceding top node.
+    pub top_nodes_delta: u32,
+    /// Top nodes storing their key in an explicit key byte.
+    pub top_nodes_explicit: u32,
+    /// Sub nodes storing their key as a delta to the preceding sub node.
+    pub sub_nodes_delta: u32,
+    /// Sub nodes storing their key in an explicit key byte.
+    pub sub_nodes_explicit: u32,
+    /// Bytes a linear scan over all nodes reads, as an estimate of the traversal cost.
+    pub scanned_bytes: u32
+}
+
+impl KeyEncodingStats {
+    /// Returns the number of key bytes saved by delta encoding, as every delta-encoded node omits its explicit key byte.
+    pub fn bytes_saved(&self) -> u32 {
+        self.top_nodes_delta + self.sub_nodes_delta
+    }
+}
+
+/// Iterator over the keys of all top nodes stored in a container in ascending order.
+pub struct TopNodeKeys<'a> {
+    container: &'a Container,
+    offset: usize,
+    end: usize,
+    last_key: Option<u8>
+}
+
+impl Iterator for TopNodeKeys<'_> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.offset < self.end {
+            let node: *const NodeHeader = unsafe { (self.container as *const Container as *const u8).add(self.offset) as *const NodeHeader };
+            let node: &NodeHeader = unsafe { &*node };
+
+            if node.as_top_node().type_flag() == NodeType::Invalid {
+                return None;
+            }
+            self.offset += node.get_offset_to_next_node();
+
+            if node.as_top_node().is_top_node() {
+                let key: u8 = match (node.as_top_node().has_delta(), self.last_key) {
+                    (true, Some(last_key)) => last_key + node.as_top_node().delta(),
+                    (true, None) => node.as_top_node().delta(),
+                    (false, _) => unsafe { *(node.as_raw() as *const u8).add(size_of::<NodeHeader>()) }
+                };
+                self.last_key = Some(key);
+                return Some(key);
+            }
+        }
+        None
     }
 }
 
@@ -97,6 +440,35 @@ pub struct EmbeddedContainer {
     pub size: u8
 }
 
+impl EmbeddedContainer {
+    /// Checks if growing this embedded container by `additional_size` bytes requires ejecting it into a standalone container.
+    ///
+    /// Returns `true`, if the configured `EmbeddingPolicy` or the size limit of the embedded container header demands an ejection.
+    /// Returns `false`, if the container can grow in place.
+    pub fn requires_ejection(&self, additional_size: usize) -> bool {
+        let required_size: usize = self.size() as usize + additional_size;
+        if required_size > CONTAINER_MAX_EMBEDDED_SIZE {
+            return true;
+        }
+
+        let config: MutexGuard<GlobalConfiguration> = global_config();
+        match config.header.embedding_policy() {
+            EmbeddingPolicy::SizeBased => required_size > config.container_embedding_limit as usize,
+            EmbeddingPolicy::EagerEject => additional_size > 0,
+            EmbeddingPolicy::MaxEmbed => false
+        }
+    }
+
+    /// Checks if a read should eject this embedded container, which spreads the cost of ejecting containers left oversized by a
+    /// lowered embedding ceiling across readers instead of leaving it to the next writer.
+    ///
+    /// Returns `true`, if ejection on reads is enabled and the container exceeds the embedding ceiling.
+    /// Returns `false`, otherwise.
+    pub fn requires_ejection_on_read(&self) -> bool {
+        global_config().header.eject_on_read() == 1 && self.requires_ejection(0)
+    }
+}
+
 #[repr(align(8))]
 pub struct ContainerLink {
     ptr: HyperionPointer
@@ -117,6 +489,295 @@ pub struct RootContainerEntry {
     pub hyperion_pointer: HyperionPointer // TODO KEY_PPP
 }
 
+impl RootContainerEntry {
+    /// Shrinks the root container of this entry to its used bytes after mass deletes and returns unused pages of its arena to the
+    /// operating system.
+    ///
+    /// Root containers of empty subtrees keep their initial size, so that the entry stays valid for subsequent puts.
+    /// Returns the number of bytes released by the container.
+    /// Returns `InvariantViolation`, if the entry has no arena.
+    pub fn shrink_to_fit(&mut self) -> Result<usize, HyperionError> {
+        let arena: &mut Arena = self.arena.borrow_mut()?;
+        let container: &mut Container = unsafe { &mut *(get_pointer(arena, &mut self.hyperion_pointer, 0, 0) as *mut Container) };
+        let released: u32 = container.trim_free_space(0);
+        if released > 0 {
+            self.hyperion_pointer = reallocate(arena, &mut self.hyperion_pointer, container.size() as usize, 0);
+        }
+        arena.release_unused();
+        Ok(released as usize)
+    }
+}
+
 pub struct RootContainer {
     pub root_container_entry: RootContainerEntry
 }
+
+#[cfg(test)]
+mod container_test {
+    use crate::hyperion::components::container::{Container,
+                                                 EmbeddedContainer,
+                                                 KeyEncodingStats,
+                                                 RootContainerEntry,
+                                                 RootContainerStats,
+                                                 CONTAINER_MAX_EMBEDDED_SIZE,
+                                                 CONTAINER_MAX_FREESIZE,
+                                                 CONTAINER_MAX_JUMP_TABLES,
+                                                 CONTAINER_MAX_SIZE};
+    use crate::hyperion::components::error::HyperionError;
+    use crate::hyperion::components::jump_table::SubNodeJumpTableEntry;
+    use crate::hyperion::components::node::{NodeType, NodeValue};
+    use crate::hyperion::components::node_header::NodeHeader;
+    use crate::hyperion::components::sub_node::SubNode;
+    use crate::hyperion::components::top_node::TopNode;
+    use crate::hyperion::internals::atomic_pointer::{initialize_container, AtomicArena};
+    use crate::hyperion::internals::core::{global_config, EmbeddingPolicy, FreeSpacePolicy, GrowthPolicy, CONFIG_LOCK};
+    use crate::memorymanager::api::{get_pointer, reallocate, Arena, HyperionPointer};
+
+    fn set_embedding(policy: EmbeddingPolicy, limit: u32) {
+        let mut config = global_config();
+        config.header.set_embedding_policy(policy);
+        config.container_embedding_limit = limit;
+    }
+
+    #[test]
+    fn test_requires_ejection() {
+        let _config_lock = CONFIG_LOCK.lock();
+        let embedded_container: EmbeddedContainer = EmbeddedContainer::new().with_size(60);
+
+        set_embedding(EmbeddingPolicy::SizeBased, 64);
+        assert!(!embedded_container.requires_ejection(4));
+        assert!(embedded_container.requires_ejection(5));
+
+        set_embedding(EmbeddingPolicy::EagerEject, 64);
+        assert!(!embedded_container.requires_ejection(0));
+        assert!(embedded_container.requires_ejection(1));
+
+        set_embedding(EmbeddingPolicy::MaxEmbed, 64);
+        assert!(!embedded_container.requires_ejection(CONTAINER_MAX_EMBEDDED_SIZE - 60));
+        assert!(embedded_container.requires_ejection(CONTAINER_MAX_EMBEDDED_SIZE - 59));
+
+        set_embedding(EmbeddingPolicy::SizeBased, 32);
+        assert!(!embedded_container.requires_ejection_on_read());
+        global_config().header.set_eject_on_read(1);
+        assert!(embedded_container.requires_ejection_on_read());
+        set_embedding(EmbeddingPolicy::SizeBased, 64);
+        assert!(!embedded_container.requires_ejection_on_read());
+        global_config().header.set_eject_on_read(0);
+
+        set_embedding(EmbeddingPolicy::SizeBased, CONTAINER_MAX_EMBEDDED_SIZE as u32);
+    }
+
+    #[test]
+    fn test_jump_table_promotion() {
+        let _config_lock = CONFIG_LOCK.lock();
+        let threshold: u32 = global_config().container_jump_table_threshold;
+        let mut container: Container = Container::new();
+
+        assert!(!container.requires_jump_table_promotion(threshold - 1));
+        assert!(container.requires_jump_table_promotion(threshold));
+        assert_eq!(container.get_target_jump_table(threshold * 3), 3);
+        assert_eq!(container.get_target_jump_table(threshold * 100), CONTAINER_MAX_JUMP_TABLES);
+
+        container.set_jump_table(2);
+        assert_eq!(container.get_target_jump_table(0), 2);
+        assert!(!container.requires_jump_table_promotion(threshold * 2));
+    }
+
+    #[test]
+    fn test_top_node_keys() {
+        let _config_lock = CONFIG_LOCK.lock();
+        let mut buffer: [u32; 8] = [0; 8];
+        let bytes: &mut [u8] = unsafe { std::slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut u8, 32) };
+        bytes[4] = TopNode::new().with_type_flag(NodeType::InnerNode).into_bits();
+        bytes[5] = b'a';
+        bytes[6] = TopNode::new().with_type_flag(NodeType::LeafNodeEmpty).with_container_type(1).with_delta(1).into_bits();
+        bytes[7] = TopNode::new().with_type_flag(NodeType::LeafNodeWithValue).with_delta(2).into_bits();
+        bytes[16] = TopNode::new().with_type_flag(NodeType::LeafNodeEmpty).into_bits();
+        bytes[17] = b'z';
+
+        let container: &mut Container = unsafe { &mut *(buffer.as_mut_ptr() as *mut Container) };
+        *container = Container::new().with_size(32).with_free_bytes(14);
+        assert_eq!(container.top_node_keys().collect::<Vec<u8>>(), vec![b'a', b'c', b'z']);
+        assert!(container.can_split());
+
+        let stats: KeyEncodingStats = container.key_encoding_stats().unwrap();
+        assert_eq!((stats.top_nodes_delta, stats.top_nodes_explicit, stats.sub_nodes_delta, stats.sub_nodes_explicit), (1, 2, 1, 0));
+        assert_eq!((stats.bytes_saved(), stats.scanned_bytes), (2, 14));
+
+        *container = Container::new().with_size(32).with_free_bytes(28);
+        assert_eq!(container.top_node_keys().count(), 0);
+        assert!(!container.can_split());
+    }
+
+    #[test]
+    fn test_increment_container_size() {
+        let _config_lock = CONFIG_LOCK.lock();
+        let increment: u32 = global_config().header.container_size_increment() as u32;
+        let mut container: Container = Container::new().with_size(increment);
+
+        assert!(!container.requires_split(1));
+        assert_eq!(container.increment_container_size(increment as i32 + 1), Ok(increment * 3));
+
+        global_config().container_growth_policy = GrowthPolicy::Exponential { percent: 50 };
+        assert_eq!(container.increment_container_size(1), Ok(increment * 5));
+        global_config().container_growth_policy = GrowthPolicy::Custom(|current_size: u32, _, _| current_size * 2);
+        assert_eq!(container.increment_container_size(1), Ok(increment * 10));
+        global_config().container_growth_policy = GrowthPolicy::Custom(|current_size: u32, _, _| current_size);
+        assert_eq!(container.increment_container_size(1), Ok(increment * 11));
+        global_config().container_growth_policy = GrowthPolicy::Linear;
+
+        container.set_size(CONTAINER_MAX_SIZE - increment + 1);
+        assert!(container.requires_split(1));
+        assert_eq!(
+            container.increment_container_size(1),
+            Err(HyperionError::ContainerSizeExceeded {
+                size: CONTAINER_MAX_SIZE + 1,
+                limit: CONTAINER_MAX_SIZE
+            })
+        );
+        assert_eq!(container.size(), CONTAINER_MAX_SIZE - increment + 1);
+    }
+
+    #[test]
+    fn test_rebuild_jump_successors() {
+        let mut buffer: [u32; 8] = [0; 8];
+        let bytes: &mut [u8] = unsafe { std::slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut u8, 32) };
+        bytes[4] = TopNode::new().with_type_flag(NodeType::InnerNode).with_jump_successor(1).into_bits();
+        bytes[5] = b'a';
+        bytes[6..8].copy_from_slice(&u16::MAX.to_le_bytes());
+        bytes[8] = TopNode::new().with_type_flag(NodeType::LeafNodeEmpty).with_container_type(1).with_delta(1).into_bits();
+        bytes[9] = TopNode::new().with_type_flag(NodeType::LeafNodeEmpty).with_jump_successor(1).with_delta(2).into_bits();
+
+        let container: &mut Container = unsafe { &mut *(buffer.as_mut_ptr() as *mut Container) };
+        *container = Container::new().with_size(32).with_free_bytes(20);
+        assert_eq!(container.rebuild_jump_successors(), Ok(2));
+
+        let bytes: &[u8] = unsafe { std::slice::from_raw_parts(buffer.as_ptr() as *const u8, 32) };
+        assert_eq!(bytes[6..8], [5, 0]);
+        assert_eq!(bytes[10..12], [3, 0]);
+
+        let container: &mut Container = unsafe { &mut *(buffer.as_mut_ptr() as *mut Container) };
+        container.set_free_bytes(21);
+        assert!(matches!(container.rebuild_jump_successors(), Err(HyperionError::Corruption(_))));
+    }
+
+    #[test]
+    fn test_trim_free_space() {
+        let _config_lock = CONFIG_LOCK.lock();
+        let increment: u32 = global_config().header.container_size_increment() as u32;
+        let mut container: Container = Container::new().with_size(increment * 4).with_free_bytes((increment * 2 + 8) as u8);
+
+        assert_eq!(container.trim_free_space(CONTAINER_MAX_FREESIZE), 0);
+        assert_eq!(container.trim_free_space(increment + 8), increment);
+        assert_eq!((container.size(), container.free_bytes() as u32), (increment * 3, increment + 8));
+        assert_eq!(container.trim_free_space(0), increment);
+        assert_eq!((container.size(), container.free_bytes() as u32), (increment * 2, 8));
+
+        let policy: FreeSpacePolicy = global_config().free_space_policy;
+        assert!(policy.trim_on_eject && !policy.trim_on_delete);
+        container.set_free_bytes((increment + 8) as u8);
+        assert_eq!(container.trim_after_delete(), 0);
+        assert_eq!(container.trim_after_eject(), 0);
+    }
+
+    #[test]
+    fn test_remove_node_value() {
+        let _config_lock = CONFIG_LOCK.lock();
+        let mut buffer: [u32; 8] = [0; 8];
+        let bytes: &mut [u8] = unsafe { std::slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut u8, 32) };
+        bytes[4] = TopNode::new().with_type_flag(NodeType::LeafNodeWithValue).with_delta(1).into_bits();
+        bytes[5..13].copy_from_slice(&42u64.to_le_bytes());
+        bytes[13] = TopNode::new().with_type_flag(NodeType::LeafNodeEmpty).with_delta(1).into_bits();
+
+        let container: &mut Container = unsafe { &mut *(buffer.as_mut_ptr() as *mut Container) };
+        *container = Container::new().with_size(32).with_free_bytes(18);
+        assert_eq!(container.remove_node_value(2), Err(HyperionError::Corruption("offset does not start a node of the container")));
+        assert_eq!(container.remove_node_value(7), Err(HyperionError::Corruption("offset does not start a node of the container")));
+        assert_eq!(container.remove_node_value(13), Ok(0));
+        assert_eq!(container.remove_node_value(4), Ok(0));
+        assert_eq!((container.size(), container.free_bytes()), (32, 26));
+        assert_eq!(container.top_node_keys().collect::<Vec<u8>>(), vec![1, 2]);
+        assert_eq!(container.remove_node_value(6), Err(HyperionError::Corruption("node exceeds the used bytes of the container")));
+
+        let bytes: &[u8] = unsafe { std::slice::from_raw_parts(buffer.as_ptr() as *const u8, 32) };
+        assert_eq!(bytes[4], TopNode::new().with_type_flag(NodeType::LeafNodeEmpty).with_delta(1).into_bits());
+        assert_eq!(bytes[5], TopNode::new().with_type_flag(NodeType::LeafNodeEmpty).with_delta(1).into_bits());
+        assert!(bytes[6..].iter().all(|byte: &u8| *byte == 0));
+
+        let mut buffer: [u32; 16] = [0; 16];
+        let bytes: &mut [u8] = unsafe { std::slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut u8, 64) };
+        bytes[4] = TopNode::new().with_type_flag(NodeType::InnerNode).with_delta(1).with_jump_table(1).into_bits();
+        bytes[5..7].copy_from_slice(&40u16.to_le_bytes());
+        bytes[35] = SubNode::new().with_type_flag(NodeType::LeafNodeWithValue).with_container_type(1).with_delta(1).into_bits();
+        bytes[36..44].copy_from_slice(&42u64.to_le_bytes());
+        bytes[44] = SubNode::new().with_type_flag(NodeType::LeafNodeEmpty).with_container_type(1).with_delta(1).into_bits();
+
+        let container: &mut Container = unsafe { &mut *(buffer.as_mut_ptr() as *mut Container) };
+        *container = Container::new().with_size(64).with_free_bytes(19);
+        assert_eq!(container.remove_node_value(35), Ok(0));
+
+        let bytes: &[u8] = unsafe { std::slice::from_raw_parts(buffer.as_ptr() as *const u8, 64) };
+        assert_eq!(u16::from_le_bytes([bytes[5], bytes[6]]), 32);
+        assert_eq!(bytes[4 + 32], SubNode::new().with_type_flag(NodeType::LeafNodeEmpty).with_container_type(1).with_delta(1).into_bits());
+    }
+
+    #[test]
+    fn test_container_le_bytes() {
+        let mut buffer: [u64; 8] = [0; 8];
+        let container: &mut Container = unsafe { &mut *(buffer.as_mut_ptr() as *mut Container) };
+        *container = Container::new().with_size(64).with_free_bytes(20).with_jump_table(1);
+        unsafe { *container.get_jump_table_pointer() = SubNodeJumpTableEntry::new().with_offset(32).with_key(b'a') };
+
+        let bytes: &mut [u8] = unsafe { std::slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut u8, 64) };
+        bytes[32] = TopNode::new().with_type_flag(NodeType::LeafNodeWithValue).with_jump_successor(1).with_delta(1).into_bits();
+        bytes[43] = TopNode::new().with_type_flag(NodeType::LeafNodeEmpty).with_delta(1).into_bits();
+        let node: &mut NodeHeader = unsafe { &mut *(bytes.as_mut_ptr().add(32) as *mut NodeHeader) };
+        node.set_jump_value(11);
+        node.write_node_value(NodeValue { v: 0x0102030405060708 });
+
+        // size 64 << 13 | free bytes 20 << 5 | jump table 1 << 2 = 0x80284, jump table entry offset 32 << 8 | key 'a' = 0x2061, a leaf
+        // with value, delta 1 and jump successor 11 at offset 32 and a leaf without value and delta 1 at offset 43
+        let mut golden: [u8; 64] = [0; 64];
+        golden[0..4].copy_from_slice(&[0x84, 0x02, 0x08, 0x00]);
+        golden[4..8].copy_from_slice(&[0x61, 0x20, 0x00, 0x00]);
+        golden[32..44].copy_from_slice(&[0xC6, 11, 0, 8, 7, 6, 5, 4, 3, 2, 1, 0x84]);
+        let bytes: &[u8] = unsafe { std::slice::from_raw_parts(buffer.as_ptr() as *const u8, 64) };
+        assert_eq!(bytes, golden);
+    }
+
+    #[test]
+    fn test_shrink_to_fit() {
+        let _config_lock = CONFIG_LOCK.lock();
+        let mut arena: Arena = Arena::default();
+        let mut atomic_arena: AtomicArena = AtomicArena::new_from_pointer(&mut arena);
+        let hyperion_pointer: HyperionPointer = initialize_container(&mut atomic_arena).unwrap();
+        let mut entry: RootContainerEntry = RootContainerEntry {
+            spinlock: 0,
+            stats: RootContainerStats {
+                puts: 0,
+                gets: 0,
+                updates: 0,
+                range_queries: 0,
+                write_lock: 0
+            },
+            arena: atomic_arena,
+            hyperion_pointer
+        };
+        assert_eq!(entry.shrink_to_fit(), Ok(0));
+
+        let arena: &mut Arena = entry.arena.borrow_mut().unwrap();
+        entry.hyperion_pointer = reallocate(arena, &mut entry.hyperion_pointer, 256, 0);
+        let container: &mut Container = unsafe { &mut *(get_pointer(arena, &mut entry.hyperion_pointer, 0, 0) as *mut Container) };
+        container.set_size(256);
+        container.set_free_bytes(252);
+
+        let increment: u32 = global_config().header.container_size_increment() as u32;
+        let expected: u32 = 252 - 252 % increment;
+        assert_eq!(entry.shrink_to_fit(), Ok(expected as usize));
+        let arena: &mut Arena = entry.arena.borrow_mut().unwrap();
+        let container: &mut Container = unsafe { &mut *(get_pointer(arena, &mut entry.hyperion_pointer, 0, 0) as *mut Container) };
+        assert_eq!((container.size(), container.free_bytes() as u32), (256 - expected, 252 - expected));
+
+        arena.lock().teardown_all_superbins();
+    }
+}
@@ -1,8 +1,8 @@
-use std::ops::DerefMut;
-
 use bitfield_struct::bitfield;
 
 use crate::hyperion::components::container::{Container, EmbeddedContainer, RootContainerEntry, CONTAINER_MAX_EMBEDDED_DEPTH};
+use crate::hyperion::components::error::HyperionError;
+use crate::hyperion::components::error::HyperionError::{DepthExceeded, InvariantViolation};
 use crate::hyperion::components::node::NodeValue;
 use crate::hyperion::components::node_header::PathCompressedNodeHeader;
 use crate::hyperion::internals::atomic_pointer::{AtomicArena,
@@ -33,6 +33,9 @@ impl OperationCommand {
 
     /// Transforms its states from an 8 bit value into a named state.
     ///
+    /// Reserved for internal constants and bitfield accessors, which cannot produce undefined values. Values read from persisted or
+    /// raw memory are decoded with [`OperationCommand::try_from_bits`] instead.
+    ///
     /// # Panics
     /// Panics if an invalid operation type was found.
     pub(crate) const fn from_bits(value: u8) -> Self {
@@ -44,6 +47,16 @@ impl OperationCommand {
             _ => panic!("Use of undefined operation type")
         }
     }
+
+    /// Transforms its states from an 8 bit value into a named state.
+    ///
+    /// Returns `HyperionError::Corruption`, if the value does not name a operation type.
+    pub fn try_from_bits(value: u8) -> Result<Self, HyperionError> {
+        match value {
+            0..=3 => Ok(Self::from_bits(value)),
+            _ => Err(HyperionError::Corruption("undefined operation type"))
+        }
+    }
 }
 
 #[repr(packed)]
@@ -106,12 +119,61 @@ pub struct ContainerInjectionContext {
 pub struct EmbeddedTraversalContext<'a> {
     pub root_container: &'a mut Container,
     pub next_embedded_container: &'a mut EmbeddedContainer,
-    pub embedded_stack: [AtomicEmbContainer; CONTAINER_MAX_EMBEDDED_DEPTH],
+    embedded_stack: Vec<AtomicEmbContainer>,
     pub next_embedded_container_offset: i32,
-    pub embedded_container_depth: i32,
     pub root_container_pointer: &'a mut HyperionPointer
 }
 
+impl<'a> EmbeddedTraversalContext<'a> {
+    /// Creates a context with an empty embedded stack, which only allocates once the first embedded container is entered.
+    pub fn new(
+        root_container: &'a mut Container, next_embedded_container: &'a mut EmbeddedContainer, root_container_pointer: &'a mut HyperionPointer
+    ) -> Self {
+        EmbeddedTraversalContext {
+            root_container,
+            next_embedded_container,
+            embedded_stack: Vec::new(),
+            next_embedded_container_offset: 0,
+            root_container_pointer
+        }
+    }
+
+    /// Returns the number of embedded containers entered so far.
+    pub fn embedded_container_depth(&self) -> usize {
+        self.embedded_stack.len()
+    }
+
+    /// Records that the traversal entered the given embedded container, which is reached via the key bytes in `prefix`.
+    ///
+    /// Returns `DepthExceeded` carrying `prefix`, if the traversal is already `CONTAINER_MAX_EMBEDDED_DEPTH` embedded containers deep.
+    pub fn push_embedded_container(&mut self, embedded_container: AtomicEmbContainer, prefix: &[u8]) -> Result<(), HyperionError> {
+        if self.embedded_stack.len() >= CONTAINER_MAX_EMBEDDED_DEPTH {
+            return Err(DepthExceeded {
+                depth: self.embedded_stack.len() + 1,
+                limit: CONTAINER_MAX_EMBEDDED_DEPTH,
+                prefix: prefix.to_vec()
+            });
+        }
+        self.embedded_stack.push(embedded_container);
+        Ok(())
+    }
+
+    /// Removes and returns the innermost embedded container, or `None`, if the traversal is not inside an embedded container.
+    pub fn pop_embedded_container(&mut self) -> Option<AtomicEmbContainer> {
+        self.embedded_stack.pop()
+    }
+
+    /// Returns the embedded container entered at the given depth, or `None`, if the traversal did not reach that depth.
+    pub fn get_embedded_container(&self, depth: usize) -> Option<&AtomicEmbContainer> {
+        self.embedded_stack.get(depth)
+    }
+
+    /// Forgets all entered embedded containers while keeping the allocated stack for reuse.
+    pub fn flush_embedded_stack(&mut self) {
+        self.embedded_stack.clear();
+    }
+}
+
 pub struct JumpTableSubContext {
     pub top_node: AtomicHeader,
     pub root_container_sub_char_set: u8,
@@ -149,11 +211,13 @@ pub struct RangeQueryContext<'a> {
     pub current_stack_depth: u16,
     pub current_key_offset: u16,
     pub key_len: u16,
+    /// Number of leading key bytes withheld from callbacks, e.g. the length of the scanned prefix, `0` reports whole keys.
+    pub suffix_offset: u16,
     pub do_report: u8,
     pub stack: [Option<TraversalContext>; 128]
 }
 
-#[bitfield(u8, order = Msb)]
+#[bitfield(u16, order = Msb)]
 pub struct OperationContextHeader {
     #[bits(2)]
     pub command: OperationCommand,
@@ -166,6 +230,12 @@ pub struct OperationContextHeader {
     #[bits(1)]
     pub pathcompressed_child: u8,
     #[bits(1)]
+    pub put_if_absent: u8,
+    /// Rejects the put on an existing key, if set to `1`. Unlike `put_if_absent`, which a single put requests, this bit carries the strict
+    /// insert option of the trie into every put.
+    #[bits(1)]
+    pub strict_inserts: u8,
+    #[bits(7)]
     __: u8
 }
 
@@ -183,6 +253,9 @@ pub struct OperationContext<'a> {
     pub path_compressed_ejection_context: Option<PathCompressedEjectionContext>,
     pub return_value: Option<&'a mut NodeValue>,
     pub input_value: Option<&'a mut NodeValue>,
+    pub expected_value: Option<NodeValue>,
+    /// Value replaced by the last put or removed by the last delete, `None` if the key did not carry a value.
+    pub old_value: Option<NodeValue>,
     pub container_injection_context: Option<ContainerInjectionContext>
 }
 
@@ -199,19 +272,77 @@ impl<'a> OperationContext<'a> {
         }
     }
 
-    pub fn get_return_value_mut(&mut self) -> &mut NodeValue {
-        self.return_value.as_deref_mut().unwrap()
+    pub fn get_return_value_mut(&mut self) -> Result<&mut NodeValue, HyperionError> {
+        self.return_value.as_deref_mut().ok_or(InvariantViolation("return value is not set"))
     }
 
-    pub fn get_input_value_mut(&mut self) -> &mut NodeValue {
-        self.input_value.as_deref_mut().unwrap()
+    pub fn get_input_value_mut(&mut self) -> Result<&mut NodeValue, HyperionError> {
+        self.input_value.as_deref_mut().ok_or(InvariantViolation("input value is not set"))
     }
 
-    pub fn get_jump_context_mut(&mut self) -> &mut JumpContext {
-        self.jump_context.as_mut().unwrap()
+    pub fn get_jump_context_mut(&mut self) -> Result<&mut JumpContext, HyperionError> {
+        self.jump_context.as_mut().ok_or(InvariantViolation("jump context is not set"))
+    }
+
+    pub fn get_embedded_traversal_context_mut(&mut self) -> Result<&mut EmbeddedTraversalContext<'a>, HyperionError> {
+        self.embedded_traversal_context.as_mut().ok_or(InvariantViolation("embedded traversal context is not set"))
+    }
+
+    pub fn get_key_as_mut(&mut self) -> Result<&mut AtomicChar, HyperionError> {
+        self.key.as_mut().ok_or(InvariantViolation("key is not set"))
+    }
+}
+
+#[cfg(test)]
+mod context_test {
+    use crate::hyperion::components::container::{Container, EmbeddedContainer, CONTAINER_MAX_EMBEDDED_DEPTH};
+    use crate::hyperion::components::context::{EmbeddedTraversalContext, OperationCommand};
+    use crate::hyperion::components::error::HyperionError;
+    use crate::hyperion::internals::atomic_pointer::AtomicEmbContainer;
+    use crate::memorymanager::api::HyperionPointer;
+
+    #[test]
+    fn test_embedded_stack() {
+        let mut root_container: Container = Container::new();
+        let mut embedded_containers: [EmbeddedContainer; CONTAINER_MAX_EMBEDDED_DEPTH + 1] =
+            [EmbeddedContainer::new(); CONTAINER_MAX_EMBEDDED_DEPTH + 1];
+        let mut next_embedded_container: EmbeddedContainer = EmbeddedContainer::new();
+        let mut root_container_pointer: HyperionPointer = HyperionPointer::new();
+        let mut context: EmbeddedTraversalContext =
+            EmbeddedTraversalContext::new(&mut root_container, &mut next_embedded_container, &mut root_container_pointer);
+        assert_eq!(context.embedded_container_depth(), 0);
+        assert!(context.get_embedded_container(0).is_none());
+
+        let key: [u8; CONTAINER_MAX_EMBEDDED_DEPTH + 1] = [b'a'; CONTAINER_MAX_EMBEDDED_DEPTH + 1];
+        for (depth, embedded_container) in embedded_containers.iter_mut().take(CONTAINER_MAX_EMBEDDED_DEPTH).enumerate() {
+            assert!(context.push_embedded_container(AtomicEmbContainer::new_from_pointer(embedded_container), &key[..=depth]).is_ok());
+        }
+        assert_eq!(context.embedded_container_depth(), CONTAINER_MAX_EMBEDDED_DEPTH);
+        assert_eq!(
+            context.push_embedded_container(AtomicEmbContainer::new_from_pointer(&mut embedded_containers[CONTAINER_MAX_EMBEDDED_DEPTH]), &key),
+            Err(HyperionError::DepthExceeded {
+                depth: CONTAINER_MAX_EMBEDDED_DEPTH + 1,
+                limit: CONTAINER_MAX_EMBEDDED_DEPTH,
+                prefix: key.to_vec()
+            })
+        );
+
+        assert_eq!(
+            context.get_embedded_container(1).map(|container: &AtomicEmbContainer| container.get()),
+            Some(&mut embedded_containers[1] as *mut EmbeddedContainer)
+        );
+        assert_eq!(
+            context.pop_embedded_container().map(|container: AtomicEmbContainer| container.get()),
+            Some(&mut embedded_containers[CONTAINER_MAX_EMBEDDED_DEPTH - 1] as *mut EmbeddedContainer)
+        );
+
+        context.flush_embedded_stack();
+        assert_eq!(context.embedded_container_depth(), 0);
     }
 
-    pub fn get_key_as_mut(&mut self) -> &mut AtomicChar {
-        self.key.as_mut().unwrap()
+    #[test]
+    fn test_operation_command_try_from_bits() {
+        assert!(matches!(OperationCommand::try_from_bits(3), Ok(OperationCommand::Delete)));
+        assert_eq!(OperationCommand::try_from_bits(4).unwrap_err(), HyperionError::Corruption("undefined operation type"));
     }
 }
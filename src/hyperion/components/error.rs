@@ -0,0 +1,57 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// Recoverable errors reported instead of aborting the host process on unexpected trie state.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum HyperionError {
+    /// Data read from a container does not describe a valid trie, e.g. an undefined node type or an out-of-bounds offset.
+    Corruption(&'static str),
+    /// An operation was started without the state it relies on, e.g. a context field that was never set up.
+    InvariantViolation(&'static str),
+    /// A key would descend deeper than the trie layout allows, e.g. past `CONTAINER_MAX_EMBEDDED_DEPTH` nested embedded containers.
+    DepthExceeded {
+        /// The depth the failing operation tried to reach.
+        depth: usize,
+        /// The maximum depth permitted by the layout.
+        limit: usize,
+        /// The key bytes leading to the container that could not be entered.
+        prefix: Vec<u8>
+    },
+    /// The key addressed by an operation is not stored.
+    KeyNotFound,
+    /// A put refused to overwrite an existing key, e.g. due to `put_if_absent` or strict inserts.
+    KeyExists,
+    /// The value stored for a key differs from the value a conditional operation expected.
+    ValueMismatch,
+    /// A put of a new key was rejected due to memory pressure and may be retried later.
+    Backpressure,
+    /// Memory for growing a container could not be allocated.
+    AllocationFailed,
+    /// A container would grow beyond the configured size cap and could not be split instead.
+    ContainerSizeExceeded {
+        /// The size in bytes the container would have grown to.
+        size: u32,
+        /// The effective size cap of containers.
+        limit: u32
+    }
+}
+
+impl Display for HyperionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HyperionError::Corruption(reason) => write!(f, "Corrupted trie: {reason}"),
+            HyperionError::InvariantViolation(reason) => write!(f, "Violated invariant: {reason}"),
+            HyperionError::DepthExceeded { depth, limit, prefix } => {
+                write!(f, "Depth {depth} exceeds the limit of {limit} below the key prefix {prefix:?}")
+            },
+            HyperionError::KeyNotFound => write!(f, "Key not found"),
+            HyperionError::KeyExists => write!(f, "Key already exists"),
+            HyperionError::ValueMismatch => write!(f, "Stored value differs from the expected value"),
+            HyperionError::Backpressure => write!(f, "Put rejected due to memory pressure"),
+            HyperionError::AllocationFailed => write!(f, "Allocation of container memory failed"),
+            HyperionError::ContainerSizeExceeded { size, limit } => write!(f, "Container size {size} exceeds the cap of {limit} bytes")
+        }
+    }
+}
+
+impl Error for HyperionError {}
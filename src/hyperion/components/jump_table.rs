@@ -3,12 +3,13 @@ use bitfield_struct::bitfield;
 pub const SUBLEVEL_JUMPTABLE_ENTRIES: usize = 15;
 pub const TOPLEVEL_JUMPTABLE_ENTRIES: usize = 7;
 
+/// Sub-node jump table of a top node, whose entries are stored in little-endian byte order.
 #[derive(Copy, Clone)]
 pub struct TopNodeJumpTable {
     pub jump: [u16; SUBLEVEL_JUMPTABLE_ENTRIES]
 }
 
-#[bitfield(u32, order = Msb)]
+#[bitfield(u32, order = Msb, repr = u32, from = u32::from_le, into = u32::to_le)]
 pub struct SubNodeJumpTableEntry {
     #[bits(24)]
     pub offset: u32,
@@ -22,3 +23,88 @@ pub struct SubNodeJumpTableEntry {
 pub struct SubNodeJumpTable {
     pub jump: [SubNodeJumpTableEntry; TOPLEVEL_JUMPTABLE_ENTRIES]
 }
+
+/// Access to the `u16` jump successor stored inside a top node.
+///
+/// The jump value is the distance in bytes from the owning top node to its successor top node within the same container.
+pub struct JumpSlot {
+    node: *mut u8,
+    offset: usize
+}
+
+impl JumpSlot {
+    /// Creates a slot for the jump value stored `offset` bytes behind the start of `node`.
+    pub fn new(node: *mut u8, offset: usize) -> Self {
+        JumpSlot { node, offset }
+    }
+
+    /// Reads the jump value as one unaligned little-endian `u16` access.
+    pub fn get(&self) -> u16 {
+        u16::from_le(unsafe { (self.node.add(self.offset) as *const u16).read_unaligned() })
+    }
+
+    /// Writes the jump value as one unaligned little-endian `u16` access, so that containers keep the same byte layout on every host.
+    pub fn set(&mut self, value: u16) {
+        unsafe { (self.node.add(self.offset) as *mut u16).write_unaligned(value.to_le()) }
+    }
+
+    /// Checks if a jump of `value` bytes from a node starting at `node_offset` lands within a container of `container_size` bytes.
+    ///
+    /// Returns `true`, if the target lies behind the slot itself and not beyond the end of the container.
+    /// Returns `false`, otherwise.
+    pub fn is_valid_jump(&self, value: u16, node_offset: usize, container_size: usize) -> bool {
+        value as usize >= self.offset + size_of::<u16>() && node_offset + value as usize <= container_size
+    }
+
+    /// Returns the container offset of the successor top node for a node starting at `node_offset`.
+    ///
+    /// # Panics
+    /// Panics in debug builds if the target lies outside of a container of `container_size` bytes.
+    pub fn target(&self, node_offset: usize, container_size: usize) -> usize {
+        let value: u16 = self.get();
+        debug_assert!(
+            self.is_valid_jump(value, node_offset, container_size),
+            "Jump of {value} bytes from offset {node_offset} leaves the container of {container_size} bytes"
+        );
+        node_offset + value as usize
+    }
+
+    /// Stores the distance between a node starting at `node_offset` and its successor at `target_offset`.
+    ///
+    /// # Panics
+    /// Panics in debug builds if the target lies outside of a container of `container_size` bytes or the distance does not fit into a
+    /// `u16`.
+    pub fn set_target(&mut self, node_offset: usize, target_offset: usize, container_size: usize) {
+        debug_assert!(target_offset >= node_offset && target_offset - node_offset <= u16::MAX as usize);
+        let value: u16 = (target_offset - node_offset) as u16;
+        debug_assert!(
+            self.is_valid_jump(value, node_offset, container_size),
+            "Jump of {value} bytes from offset {node_offset} leaves the container of {container_size} bytes"
+        );
+        self.set(value);
+    }
+}
+
+#[cfg(test)]
+mod jump_table_test {
+    use crate::hyperion::components::jump_table::JumpSlot;
+    use crate::hyperion::components::node::NodeType;
+    use crate::hyperion::components::node_header::NodeHeader;
+    use crate::hyperion::components::top_node::TopNode;
+
+    #[test]
+    fn test_jump_slot() {
+        let mut container: [u8; 16] = [0; 16];
+        container[4] = TopNode::new().with_type_flag(NodeType::InnerNode).with_jump_successor(1).into_bits();
+
+        let node: &mut NodeHeader = unsafe { (container.as_mut_ptr().add(4) as *mut NodeHeader).as_mut().unwrap() };
+        let mut slot: JumpSlot = node.jump_slot();
+        slot.set_target(4, 12, container.len());
+
+        assert_eq!(node.get_jump_value(), 8);
+        assert_eq!(container[6..8], [8, 0]);
+        assert_eq!(node.jump_slot().target(4, container.len()), 12);
+        assert!(!node.jump_slot().is_valid_jump(2, 4, container.len()));
+        assert!(!node.jump_slot().is_valid_jump(13, 4, container.len()));
+    }
+}
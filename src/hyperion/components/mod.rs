@@ -1,5 +1,8 @@
+#![cfg_attr(not(test), deny(clippy::unwrap_used))]
+
 pub mod container;
 pub mod context;
+pub mod error;
 pub mod jump_table;
 pub mod node;
 pub mod node_header;
@@ -1,4 +1,5 @@
 use crate::hyperion::components::context::{ContainerTraversalContext, OperationContext};
+use crate::hyperion::components::error::HyperionError;
 use crate::hyperion::components::node_header::NodeHeader;
 
 #[derive(Debug, PartialEq, PartialOrd)]
@@ -17,6 +18,9 @@ impl NodeType {
 
     /// Transforms its states from an 8 bit value into a named state.
     ///
+    /// Reserved for internal constants and bitfield accessors, which cannot produce undefined values. Values read from persisted or
+    /// raw memory are decoded with [`NodeType::try_from_bits`] instead.
+    ///
     /// # Panics
     /// Panics if an invalid node type was found.
     pub(crate) const fn from_bits(value: u8) -> Self {
@@ -28,12 +32,43 @@ impl NodeType {
             _ => panic!("Use of undefined node type")
         }
     }
+
+    /// Transforms its states from an 8 bit value into a named state.
+    ///
+    /// Returns `HyperionError::Corruption`, if the value does not name a node type.
+    pub fn try_from_bits(value: u8) -> Result<Self, HyperionError> {
+        match value {
+            0..=3 => Ok(Self::from_bits(value)),
+            _ => Err(HyperionError::Corruption("undefined node type"))
+        }
+    }
+
+    /// Returns `true`, if a node of this type terminates a stored key.
+    /// Returns `false`, otherwise.
+    pub fn is_leaf(&self) -> bool {
+        *self == NodeType::LeafNodeEmpty || *self == NodeType::LeafNodeWithValue
+    }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct NodeValue {
     pub v: u64
 }
 
+impl NodeValue {
+    /// Reads a value stored in little-endian byte order from a slot of any alignment.
+    pub(crate) unsafe fn read_le(slot: *const NodeValue) -> NodeValue {
+        NodeValue {
+            v: u64::from_le(slot.read_unaligned().v)
+        }
+    }
+
+    /// Writes this value in little-endian byte order into a slot of any alignment.
+    pub(crate) unsafe fn write_le(self, slot: *mut NodeValue) {
+        slot.write_unaligned(NodeValue { v: self.v.to_le() })
+    }
+}
+
 pub struct Node {
     pub header: NodeHeader,
     pub stored_value: u8
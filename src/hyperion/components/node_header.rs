@@ -5,16 +5,23 @@ use libc::{memcmp, size_t};
 
 use crate::hyperion::components::container::{ContainerLink, EmbeddedContainer};
 use crate::hyperion::components::context::{ContainerTraversalContext, JumpContext, OperationContext, RangeQueryContext};
-use crate::hyperion::components::jump_table::TopNodeJumpTable;
+use crate::hyperion::components::error::HyperionError;
+use crate::hyperion::components::error::HyperionError::InvariantViolation;
+use crate::hyperion::components::jump_table::{JumpSlot, TopNodeJumpTable};
 use crate::hyperion::components::node::NodeType::{InnerNode, Invalid, LeafNodeEmpty, LeafNodeWithValue};
 use crate::hyperion::components::node::{NodeType, NodeValue};
 use crate::hyperion::components::return_codes::ReturnCode;
-use crate::hyperion::components::return_codes::ReturnCode::{GetFailureNoLeaf, OK};
+use crate::hyperion::components::return_codes::ReturnCode::{CompareAndSwapFailureValueMismatch,
+                                                            DeleteFailureValueMismatch,
+                                                            GetFailureNoLeaf,
+                                                            PutFailureBackpressure,
+                                                            PutFailureKeyExists,
+                                                            PutFailureValueaddNeedsShift,
+                                                            OK};
 use crate::hyperion::components::sub_node::{ChildLinkType, SubNode};
 use crate::hyperion::components::top_node::TopNode;
-use crate::hyperion::internals::atomic_pointer::{AtomicChar, AtomicHeader, AtomicNodeValue, AtomicPointer};
-use crate::hyperion::internals::core::HyperionCallback;
-use crate::hyperion::internals::helpers::{copy_memory_from, copy_memory_to};
+use crate::hyperion::internals::atomic_pointer::{AtomicChar, AtomicHeader, AtomicNodeValue, AtomicPointer, Atomicu8};
+use crate::hyperion::internals::core::{global_config, is_load_shedding, HyperionCallback, MergeFn};
 
 #[repr(C)]
 #[derive(Clone, Copy)]
@@ -61,7 +68,7 @@ impl NodeHeader {
     }
 
     pub fn as_path_compressed(&self) -> &PathCompressedNodeHeader {
-        unsafe { self.as_raw_compressed().as_ref().unwrap() }
+        unsafe { &*self.as_raw_compressed() }
     }
 
     pub fn as_raw_embedded(&self, offset: usize) -> *const EmbeddedContainer {
@@ -169,31 +176,67 @@ impl NodeHeader {
         size_of::<NodeHeader>()
     }
 
+    pub fn jump_slot(&mut self) -> JumpSlot {
+        debug_assert!(self.as_top_node().jump_successor() == 1, "Node has no jump successor");
+        JumpSlot::new(self.self_as_raw_mut() as *mut u8, self.get_offset_jump())
+    }
+
     pub fn get_jump_value(&self) -> u16 {
-        let self_pointer: *const NodeHeader = self as *const NodeHeader;
-        unsafe { *(self_pointer.add(self.get_offset_jump()) as *const u16) }
+        debug_assert!(self.as_top_node().jump_successor() == 1, "Node has no jump successor");
+        u16::from_le(unsafe { ((self.as_raw() as *const u8).add(self.get_offset_jump()) as *const u16).read_unaligned() })
+    }
+
+    pub fn set_jump_value(&mut self, value: u16) {
+        self.jump_slot().set(value)
     }
 
     pub fn get_offset_jump_table(&self) -> u16 {
         self.get_offset_jump() as u16 + self.as_top_node().jump_successor() as u16 * size_of::<u16>() as u16
     }
 
-    fn get_node_value_pc(&self, operation_context: &mut OperationContext) -> ReturnCode {
-        let pc_head: &PathCompressedNodeHeader = self.as_path_compressed();
-        if pc_head.value_present() > 0 {
-            unsafe {
-                copy_memory_from(
-                    pc_head.as_raw_char().add(size_of::<PathCompressedNodeHeader>()),
-                    operation_context.get_return_value_mut() as *mut NodeValue,
-                    size_of::<NodeValue>()
-                )
-            }
+    /// Returns a pointer to the value slot of this node for reading.
+    ///
+    /// The slot follows the delta byte and the jump overhead, so it is aligned only by chance. The value is stored in little-endian byte
+    /// order, access it through [`NodeHeader::read_node_value`] and [`NodeHeader::write_node_value`].
+    pub fn node_value_slot(&self) -> *const NodeValue {
+        unsafe { (self.as_raw() as *const u8).add(self.get_offset_node_value()) as *const NodeValue }
+    }
+
+    /// Returns a pointer to the value slot of this node for writing, see [`NodeHeader::node_value_slot`].
+    pub fn node_value_slot_mut(&mut self) -> *mut NodeValue {
+        let offset: usize = self.get_offset_node_value();
+        unsafe { (self.self_as_raw_mut() as *mut u8).add(offset) as *mut NodeValue }
+    }
+
+    /// Returns `true`, if the value slot of this node satisfies the alignment of `NodeValue`.
+    /// Returns `false`, otherwise.
+    pub fn is_node_value_aligned(&self) -> bool {
+        self.node_value_slot().is_aligned()
+    }
+
+    /// Reads the value stored in this top or sub node without requiring mutable access to the container or any traversal context.
+    /// Returns `None`, if the node is not a leaf carrying a value.
+    pub fn read_node_value(&self) -> Option<NodeValue> {
+        if self.as_top_node().type_flag() != LeafNodeWithValue {
+            return None;
+        }
+        unsafe { Some(NodeValue::read_le(self.node_value_slot())) }
+    }
+
+    /// Writes the given value in little-endian byte order into the value slot of this node regardless of the slot's alignment.
+    pub fn write_node_value(&mut self, value: NodeValue) {
+        unsafe { value.write_le(self.node_value_slot_mut()) }
+    }
+
+    fn get_node_value_pc(&self, operation_context: &mut OperationContext) -> Result<ReturnCode, HyperionError> {
+        if let Some(value) = self.as_path_compressed().read_value() {
+            *operation_context.get_return_value_mut()? = value;
         }
         operation_context.header.set_operation_done(1);
-        OK
+        Ok(OK)
     }
 
-    pub fn get_node_value(&self, operation_context: &mut OperationContext) -> ReturnCode {
+    pub fn get_node_value(&self, operation_context: &mut OperationContext) -> Result<ReturnCode, HyperionError> {
         if operation_context.header.pathcompressed_child() == 1 {
             return self.get_node_value_pc(operation_context);
         }
@@ -201,45 +244,152 @@ impl NodeHeader {
         let top_node_type: NodeType = self.as_top_node().type_flag();
 
         if top_node_type == InnerNode || top_node_type == Invalid {
-            return GetFailureNoLeaf;
+            return Ok(GetFailureNoLeaf);
         }
 
-        if top_node_type == LeafNodeWithValue {
-            unsafe {
-                copy_memory_from(
-                    self.as_raw_char().add(self.get_offset_node_value()),
-                    operation_context.get_return_value_mut() as *mut NodeValue,
-                    size_of::<NodeValue>()
-                );
-            }
+        if let Some(value) = self.read_node_value() {
+            *operation_context.get_return_value_mut()? = value;
         }
 
         operation_context.header.set_operation_done(1);
-        OK
+        Ok(OK)
     }
 
-    pub fn set_node_value(&mut self, operation_context: &mut OperationContext) -> ReturnCode {
+    pub fn set_node_value(&mut self, operation_context: &mut OperationContext) -> Result<ReturnCode, HyperionError> {
         let top_node: &mut TopNode = self.as_top_node_mut();
 
+        if top_node.type_flag().is_leaf() && (operation_context.header.put_if_absent() == 1 || operation_context.header.strict_inserts() == 1) {
+            operation_context.header.set_operation_done(1);
+            return Ok(PutFailureKeyExists);
+        }
+
+        if !top_node.type_flag().is_leaf() && is_load_shedding() {
+            operation_context.header.set_operation_done(1);
+            return Ok(PutFailureBackpressure);
+        }
+
         if top_node.type_flag() == Invalid || top_node.type_flag() == InnerNode {
             operation_context.header.set_performed_put(1);
         }
 
-        if operation_context.input_value.is_some() {
-            let input_value: &mut NodeValue = operation_context.get_input_value_mut();
-            unsafe {
-                copy_memory_to(self.as_raw_char_mut().add(self.get_offset_node_value()), input_value as *const NodeValue, size_of::<NodeValue>());
-            }
+        operation_context.old_value = self.read_node_value();
+        if let Some(input_value) = operation_context.input_value.as_deref_mut() {
+            let value: NodeValue = *input_value;
+            self.write_node_value(value);
             self.as_top_node_mut().set_type_flag(LeafNodeWithValue);
         } else {
             self.as_top_node_mut().set_type_flag(LeafNodeEmpty);
         }
         operation_context.header.set_operation_done(1);
-        OK
+        Ok(OK)
+    }
+
+    /// Merges the operand passed as input value into the value stored in this node using the registered merge operator and stores the
+    /// result. The merged value is written back into the input value.
+    ///
+    /// Merges update existing keys by design, so they are exempt from `put_if_absent` and strict inserts.
+    /// Returns `PutFailureBackpressure`, if the key is new and puts of new keys are shed, leaving the operand untouched.
+    /// Returns `InvariantViolation`, if no merge operator is registered or no operand is set.
+    pub fn merge_node_value(&mut self, operation_context: &mut OperationContext) -> Result<ReturnCode, HyperionError> {
+        let merge_operator: MergeFn = global_config().merge_operator.ok_or(InvariantViolation("merge operator is not registered"))?;
+        let type_flag: NodeType = self.as_top_node().type_flag();
+
+        if !type_flag.is_leaf() && is_load_shedding() {
+            operation_context.header.set_operation_done(1);
+            return Ok(PutFailureBackpressure);
+        }
+
+        let old_value: Option<NodeValue> = self.read_node_value();
+        let operand: &mut NodeValue = operation_context.get_input_value_mut()?;
+        *operand = merge_operator(old_value.as_ref(), operand);
+        let merged_value: NodeValue = *operand;
+
+        if type_flag == Invalid || type_flag == InnerNode {
+            operation_context.header.set_performed_put(1);
+        }
+        operation_context.old_value = old_value;
+        self.write_node_value(merged_value);
+        self.as_top_node_mut().set_type_flag(LeafNodeWithValue);
+        operation_context.header.set_operation_done(1);
+        Ok(OK)
+    }
+
+    /// Replaces the value stored in this node with the input value, if it equals the expected value, without another traversal.
+    ///
+    /// The stored value is copied into `old_value` in either case. The compare and the write are plain unaligned accesses, not an atomic
+    /// instruction, so the caller has to hold the lock of the container.
+    /// Returns `OK`, if the value was swapped.
+    /// Returns `CompareAndSwapFailureValueMismatch`, if the stored value differs from the expected value.
+    /// Returns `PutFailureValueaddNeedsShift`, if this leaf carries no value, as adding one requires shifting the container.
+    /// Returns `GetFailureNoLeaf`, if this node does not store a key.
+    pub fn compare_and_swap_node_value(&mut self, operation_context: &mut OperationContext) -> Result<ReturnCode, HyperionError> {
+        let Some(stored_value) = self.read_node_value() else {
+            return Ok(if self.as_top_node().type_flag().is_leaf() {
+                PutFailureValueaddNeedsShift
+            } else {
+                GetFailureNoLeaf
+            });
+        };
+        operation_context.old_value = Some(stored_value);
+        if operation_context.expected_value != Some(stored_value) {
+            operation_context.header.set_operation_done(1);
+            return Ok(CompareAndSwapFailureValueMismatch);
+        }
+        let value: NodeValue = *operation_context.get_input_value_mut()?;
+        self.write_node_value(value);
+        operation_context.header.set_operation_done(1);
+        Ok(OK)
+    }
+
+    /// Adds the input value to the value stored in this node in place, wrapping on overflow. The sum is written back into the input value
+    /// and the previous value into `old_value`.
+    ///
+    /// The read and the write are plain unaligned accesses, not an atomic instruction, so the caller has to hold the lock of the container.
+    /// Returns `OK`, if the value was updated.
+    /// Returns `PutFailureValueaddNeedsShift`, if this leaf carries no value, as adding one requires shifting the container.
+    /// Returns `GetFailureNoLeaf`, if this node does not store a key.
+    pub fn fetch_add_node_value(&mut self, operation_context: &mut OperationContext) -> Result<ReturnCode, HyperionError> {
+        let Some(stored_value) = self.read_node_value() else {
+            return Ok(if self.as_top_node().type_flag().is_leaf() {
+                PutFailureValueaddNeedsShift
+            } else {
+                GetFailureNoLeaf
+            });
+        };
+        let delta: &mut NodeValue = operation_context.get_input_value_mut()?;
+        delta.v = stored_value.v.wrapping_add(delta.v);
+        let value: NodeValue = *delta;
+        self.write_node_value(value);
+        operation_context.old_value = Some(stored_value);
+        operation_context.header.set_operation_done(1);
+        Ok(OK)
+    }
+
+    /// Checks the condition of a conditional delete against the value stored in this node.
+    ///
+    /// If the delete may proceed, the stored value is copied into `old_value` before the node is unlinked.
+    ///
+    /// Returns `OK`, if this node is a leaf and no expected value is set or the stored value equals the expected value.
+    /// Returns `GetFailureNoLeaf`, if this node does not store a key.
+    /// Returns `DeleteFailureValueMismatch`, otherwise.
+    pub fn check_delete_condition(&self, operation_context: &mut OperationContext) -> ReturnCode {
+        if !self.as_top_node().type_flag().is_leaf() {
+            return GetFailureNoLeaf;
+        }
+        let stored_value: Option<NodeValue> = self.read_node_value();
+        match operation_context.expected_value {
+            Some(expected_value) if stored_value != Some(expected_value) => DeleteFailureValueMismatch,
+            _ => {
+                operation_context.old_value = stored_value;
+                OK
+            }
+        }
     }
 
-    pub fn register_jump_context(&mut self, container_traversal_context: &mut ContainerTraversalContext, operation_context: &mut OperationContext) {
-        let jump_context: &mut JumpContext = operation_context.get_jump_context_mut();
+    pub fn register_jump_context(
+        &mut self, container_traversal_context: &mut ContainerTraversalContext, operation_context: &mut OperationContext
+    ) -> Result<(), HyperionError> {
+        let jump_context: &mut JumpContext = operation_context.get_jump_context_mut()?;
         if self.as_top_node().jump_successor() == 1 {
             jump_context.predecessor = AtomicHeader::new_from_pointer(self.self_as_raw_mut());
             jump_context.sub_nodes_seen = 0;
@@ -247,20 +397,51 @@ impl NodeHeader {
         } else {
             jump_context.predecessor = AtomicPointer::new();
         }
+        Ok(())
+    }
+
+    /// Reports a key of `key_len` bytes to the callback, skipping the first `suffix_offset` bytes of the current key.
+    fn report_key(
+        range_query_context: &mut RangeQueryContext, key_len: u16, value: &mut AtomicNodeValue, hyperion_callback: HyperionCallback<NodeValue>
+    ) -> bool {
+        let suffix_offset: u16 = range_query_context.suffix_offset.min(key_len);
+        if suffix_offset == 0 {
+            return hyperion_callback(&mut range_query_context.current_key, key_len, value);
+        }
+        let mut suffix: Atomicu8 = Atomicu8::new_from_pointer(range_query_context.current_key.add_get(suffix_offset as usize));
+        hyperion_callback(&mut suffix, key_len - suffix_offset, value)
+    }
+
+    /// Passes the value of this leaf to the callback. Misaligned value slots are handed over as an aligned copy, which is written back
+    /// afterwards, so that callbacks may safely borrow the value on targets that fault on unaligned accesses. Big-endian hosts always
+    /// receive a copy in native byte order.
+    fn call_with_node_value(
+        &mut self, range_query_context: &mut RangeQueryContext, key_offset: u16, hyperion_callback: HyperionCallback<NodeValue>
+    ) -> bool {
+        let key_len: u16 = range_query_context.current_key_offset + key_offset;
+        if cfg!(target_endian = "little") && self.is_node_value_aligned() {
+            return NodeHeader::report_key(
+                range_query_context,
+                key_len,
+                &mut AtomicNodeValue::new_from_pointer(self.node_value_slot_mut()),
+                hyperion_callback
+            );
+        }
+
+        let mut value: NodeValue = unsafe { NodeValue::read_le(self.node_value_slot()) };
+        let result: bool =
+            NodeHeader::report_key(range_query_context, key_len, &mut AtomicNodeValue::new_from_pointer(&mut value), hyperion_callback);
+        self.write_node_value(value);
+        result
     }
 
     pub fn call_top_node(&mut self, range_query_context: &mut RangeQueryContext, hyperion_callback: HyperionCallback<NodeValue>) -> bool {
         match self.as_top_node().type_flag() {
             LeafNodeEmpty => {
-                hyperion_callback(&mut range_query_context.current_key, range_query_context.current_key_offset + 1, &mut AtomicNodeValue::new())
-            },
-            LeafNodeWithValue => unsafe {
-                hyperion_callback(
-                    &mut range_query_context.current_key,
-                    range_query_context.current_key_offset + 1,
-                    &mut AtomicNodeValue::new_from_pointer(self.self_as_raw_mut().add(self.get_offset_node_value()) as *mut NodeValue)
-                )
+                let key_len: u16 = range_query_context.current_key_offset + 1;
+                NodeHeader::report_key(range_query_context, key_len, &mut AtomicNodeValue::new(), hyperion_callback)
             },
+            LeafNodeWithValue => self.call_with_node_value(range_query_context, 1, hyperion_callback),
             Invalid | InnerNode => true
         }
     }
@@ -268,33 +449,28 @@ impl NodeHeader {
     pub fn call_sub_node(&mut self, range_query_context: &mut RangeQueryContext, hyperion_callback: HyperionCallback<NodeValue>) -> bool {
         match self.as_sub_node().type_flag() {
             LeafNodeEmpty => {
-                hyperion_callback(&mut range_query_context.current_key, range_query_context.current_key_offset + 2, &mut AtomicNodeValue::new())
-            },
-            LeafNodeWithValue => unsafe {
-                hyperion_callback(
-                    &mut range_query_context.current_key,
-                    range_query_context.current_key_offset + 2,
-                    &mut AtomicNodeValue::new_from_pointer(self.self_as_raw_mut().add(self.get_offset_node_value()) as *mut NodeValue)
-                )
+                let key_len: u16 = range_query_context.current_key_offset + 2;
+                NodeHeader::report_key(range_query_context, key_len, &mut AtomicNodeValue::new(), hyperion_callback)
             },
+            LeafNodeWithValue => self.call_with_node_value(range_query_context, 2, hyperion_callback),
             Invalid | InnerNode => true
         }
     }
 
-    pub fn compare_path_compressed_node(&self, operation_context: &mut OperationContext) -> bool {
-        let pc_header: &PathCompressedNodeHeader = unsafe { self.as_raw_compressed().as_ref().unwrap() };
+    pub fn compare_path_compressed_node(&self, operation_context: &mut OperationContext) -> Result<bool, HyperionError> {
+        let pc_header: &PathCompressedNodeHeader = unsafe { &*self.as_raw_compressed() };
 
         let overhead: usize = size_of::<PathCompressedNodeHeader>() + pc_header.value_present() as usize * size_of::<NodeValue>();
         let key_len: u8 = pc_header.size() - overhead as u8;
 
         if operation_context.key_len_left - 2 != key_len as i32 {
-            return false;
+            return Ok(false);
         }
 
-        let op_key: &mut AtomicChar = operation_context.get_key_as_mut();
+        let op_key: &mut AtomicChar = operation_context.get_key_as_mut()?;
         unsafe {
             let key: *const PathCompressedNodeHeader = (pc_header as *const PathCompressedNodeHeader).add(overhead);
-            memcmp(op_key.add_get(2) as *mut c_void, key as *mut c_void, key_len as size_t) == 0
+            Ok(memcmp(op_key.add_get(2) as *mut c_void, key as *mut c_void, key_len as size_t) == 0)
         }
     }
 }
@@ -316,4 +492,338 @@ impl PathCompressedNodeHeader {
     pub fn as_raw_char(&self) -> *const char {
         self.as_raw() as *const char
     }
+
+    /// Reads the value stored behind this path compressed node header.
+    /// Returns `None`, if no value is present.
+    pub fn read_value(&self) -> Option<NodeValue> {
+        if self.value_present() == 0 {
+            return None;
+        }
+        unsafe { Some(NodeValue::read_le((self.as_raw() as *const u8).add(size_of::<PathCompressedNodeHeader>()) as *const NodeValue)) }
+    }
+}
+
+#[cfg(test)]
+mod node_header_test {
+    use std::sync::Mutex;
+
+    use crate::hyperion::components::context::{OperationCommand, OperationContext, OperationContextHeader, RangeQueryContext};
+    use crate::hyperion::components::node::{NodeType, NodeValue};
+    use crate::hyperion::components::node_header::{NodeHeader, PathCompressedNodeHeader};
+    use crate::hyperion::components::return_codes::ReturnCode;
+    use crate::hyperion::components::top_node::TopNode;
+    use crate::hyperion::internals::atomic_pointer::{AtomicArena, AtomicChar, AtomicNodeValue, Atomicu8};
+    use crate::hyperion::internals::core::{global_config, update_load_shedding, CONFIG_LOCK};
+
+    fn operation_context<'a>(command: OperationCommand, input_value: Option<&'a mut NodeValue>) -> OperationContext<'a> {
+        OperationContext {
+            header: OperationContextHeader::new().with_command(command),
+            chained_pointer_hook: 0,
+            key_len_left: 0,
+            key: None,
+            jump_context: None,
+            root_container_entry: None,
+            embedded_traversal_context: None,
+            jump_table_sub_context: None,
+            next_container_pointer: None,
+            arena: None,
+            path_compressed_ejection_context: None,
+            return_value: None,
+            input_value,
+            expected_value: None,
+            old_value: None,
+            container_injection_context: None
+        }
+    }
+
+    #[test]
+    fn test_read_node_value() {
+        let mut node: [u8; 16] = [0; 16];
+        node[0] = TopNode::new().with_type_flag(NodeType::LeafNodeWithValue).with_delta(1).into_bits();
+        let header: &NodeHeader = unsafe { &*(node.as_ptr() as *const NodeHeader) };
+        let offset: usize = header.get_offset_node_value();
+        node[offset..offset + 8].copy_from_slice(&42u64.to_ne_bytes());
+
+        let header: &NodeHeader = unsafe { &*(node.as_ptr() as *const NodeHeader) };
+        assert_eq!(header.read_node_value(), Some(NodeValue { v: 42 }));
+
+        node[0] = TopNode::new().with_type_flag(NodeType::InnerNode).with_delta(1).into_bits();
+        let header: &NodeHeader = unsafe { &*(node.as_ptr() as *const NodeHeader) };
+        assert_eq!(header.read_node_value(), None);
+    }
+
+    #[test]
+    fn test_call_with_misaligned_node_value() {
+        fn increment(_key: &mut Atomicu8, key_len: u16, value: &mut AtomicNodeValue) -> bool {
+            value.borrow_mut().unwrap().v += key_len as u64;
+            true
+        }
+
+        let mut node: [u64; 3] = [0; 3];
+        let bytes: *mut u8 = node.as_mut_ptr() as *mut u8;
+        unsafe { *bytes = TopNode::new().with_type_flag(NodeType::LeafNodeWithValue).with_delta(1).into_bits() };
+        let header: &mut NodeHeader = unsafe { &mut *(bytes as *mut NodeHeader) };
+        assert!(!header.is_node_value_aligned());
+        header.write_node_value(NodeValue { v: 40 });
+
+        let mut arena: AtomicArena = AtomicArena::new();
+        let mut range_query_context: RangeQueryContext = RangeQueryContext {
+            key_begin: AtomicChar::new(),
+            current_key: Atomicu8::new(),
+            arena: &mut arena,
+            current_stack_depth: 0,
+            current_key_offset: 1,
+            key_len: 0,
+            suffix_offset: 0,
+            do_report: 0,
+            stack: [const { None }; 128]
+        };
+        assert!(header.call_top_node(&mut range_query_context, increment));
+        assert_eq!(header.read_node_value(), Some(NodeValue { v: 42 }));
+    }
+
+    #[test]
+    fn test_read_path_compressed_value() {
+        let mut node: [u8; 9] = [0; 9];
+        node[0] = PathCompressedNodeHeader::new().with_size(9).with_value_present(1).into_bits();
+        node[1..9].copy_from_slice(&7u64.to_ne_bytes());
+
+        let header: &PathCompressedNodeHeader = unsafe { &*(node.as_ptr() as *const PathCompressedNodeHeader) };
+        assert_eq!(header.read_value(), Some(NodeValue { v: 7 }));
+
+        node[0] = PathCompressedNodeHeader::new().with_size(1).into_bits();
+        let header: &PathCompressedNodeHeader = unsafe { &*(node.as_ptr() as *const PathCompressedNodeHeader) };
+        assert_eq!(header.read_value(), None);
+    }
+
+    #[test]
+    fn test_put_if_absent() {
+        let _config_lock = CONFIG_LOCK.lock();
+        let mut node: [u8; 16] = [0; 16];
+        node[0] = TopNode::new().with_type_flag(NodeType::LeafNodeWithValue).with_delta(1).into_bits();
+        let header: &mut NodeHeader = unsafe { &mut *(node.as_mut_ptr() as *mut NodeHeader) };
+        let offset: usize = header.get_offset_node_value();
+        node[offset..offset + 8].copy_from_slice(&42u64.to_ne_bytes());
+
+        let mut value: NodeValue = NodeValue { v: 7 };
+        let mut context: OperationContext = operation_context(OperationCommand::Put, Some(&mut value));
+        context.header.set_put_if_absent(1);
+
+        let header: &mut NodeHeader = unsafe { &mut *(node.as_mut_ptr() as *mut NodeHeader) };
+        assert!(matches!(header.set_node_value(&mut context), Ok(ReturnCode::PutFailureKeyExists)));
+        assert_eq!(context.header.performed_put(), 0);
+        assert_eq!(header.read_node_value(), Some(NodeValue { v: 42 }));
+    }
+
+    #[test]
+    fn test_old_value() {
+        let mut node: [u8; 16] = [0; 16];
+        node[0] = TopNode::new().with_type_flag(NodeType::InnerNode).with_delta(1).into_bits();
+        let header: &mut NodeHeader = unsafe { &mut *(node.as_mut_ptr() as *mut NodeHeader) };
+
+        let mut value: NodeValue = NodeValue { v: 7 };
+        let mut context: OperationContext = operation_context(OperationCommand::Put, Some(&mut value));
+        let _config_lock = CONFIG_LOCK.lock();
+        assert!(matches!(header.set_node_value(&mut context), Ok(ReturnCode::OK)));
+        assert_eq!(context.old_value, None);
+
+        let mut value: NodeValue = NodeValue { v: 42 };
+        let mut context: OperationContext = operation_context(OperationCommand::Put, Some(&mut value));
+        assert!(matches!(header.set_node_value(&mut context), Ok(ReturnCode::OK)));
+        assert_eq!(context.old_value, Some(NodeValue { v: 7 }));
+        assert_eq!(header.read_node_value(), Some(NodeValue { v: 42 }));
+    }
+
+    #[test]
+    fn test_strict_inserts() {
+        let mut node: [u8; 16] = [0; 16];
+        node[0] = TopNode::new().with_type_flag(NodeType::LeafNodeEmpty).with_delta(1).into_bits();
+
+        let mut value: NodeValue = NodeValue { v: 7 };
+        let mut context: OperationContext = operation_context(OperationCommand::Put, Some(&mut value));
+        let header: &mut NodeHeader = unsafe { &mut *(node.as_mut_ptr() as *mut NodeHeader) };
+        context.header.set_strict_inserts(1);
+
+        let _config_lock = CONFIG_LOCK.lock();
+        assert!(matches!(header.set_node_value(&mut context), Ok(ReturnCode::PutFailureKeyExists)));
+        assert_eq!(header.as_top_node().type_flag(), NodeType::LeafNodeEmpty);
+    }
+
+    #[test]
+    fn test_load_shedding() {
+        static TRANSITIONS: Mutex<Vec<bool>> = Mutex::new(vec![]);
+        fn record(active: bool) {
+            TRANSITIONS.lock().unwrap().push(active);
+        }
+
+        let mut node: [u8; 24] = [0; 24];
+        node[0] = TopNode::new().with_type_flag(NodeType::InnerNode).with_delta(1).into_bits();
+        node[8] = TopNode::new().with_type_flag(NodeType::LeafNodeEmpty).with_delta(1).into_bits();
+        let mut value: NodeValue = NodeValue { v: 7 };
+        let mut context: OperationContext = operation_context(OperationCommand::Put, Some(&mut value));
+
+        let _config_lock = CONFIG_LOCK.lock();
+        {
+            let mut config = global_config();
+            config.load_shedding_high_watermark = 100;
+            config.load_shedding_low_watermark = 50;
+            config.load_shedding_callback = Some(record);
+        }
+        assert!(!update_load_shedding(99));
+        assert!(update_load_shedding(100));
+        assert!(update_load_shedding(50));
+
+        let new_key: &mut NodeHeader = unsafe { &mut *(node.as_mut_ptr() as *mut NodeHeader) };
+        assert!(matches!(new_key.set_node_value(&mut context), Ok(ReturnCode::PutFailureBackpressure)));
+        assert_eq!(new_key.as_top_node().type_flag(), NodeType::InnerNode);
+
+        let existing_key: &mut NodeHeader = unsafe { &mut *(node.as_mut_ptr().add(8) as *mut NodeHeader) };
+        assert!(matches!(existing_key.set_node_value(&mut context), Ok(ReturnCode::OK)));
+        assert_eq!(existing_key.read_node_value(), Some(NodeValue { v: 7 }));
+
+        assert!(!update_load_shedding(49));
+
+        for low_watermark in [0, 200] {
+            global_config().load_shedding_low_watermark = low_watermark;
+            assert!(update_load_shedding(100));
+            assert!(!update_load_shedding(99));
+        }
+        {
+            let mut config = global_config();
+            config.load_shedding_high_watermark = 0;
+            config.load_shedding_low_watermark = 0;
+            config.load_shedding_callback = None;
+        }
+        assert_eq!(*TRANSITIONS.lock().unwrap(), vec![true, false, true, false, true, false]);
+    }
+
+    #[test]
+    fn test_merge_node_value() {
+        fn add(old: Option<&NodeValue>, operand: &NodeValue) -> NodeValue {
+            NodeValue {
+                v: old.map_or(0, |value: &NodeValue| value.v) + operand.v
+            }
+        }
+        let _config_lock = CONFIG_LOCK.lock();
+        global_config().merge_operator = Some(add);
+
+        let mut node: [u8; 16] = [0; 16];
+        node[0] = TopNode::new().with_type_flag(NodeType::LeafNodeEmpty).with_delta(1).into_bits();
+        let header: &mut NodeHeader = unsafe { &mut *(node.as_mut_ptr() as *mut NodeHeader) };
+
+        let mut operand: NodeValue = NodeValue { v: 8 };
+        let mut context: OperationContext = operation_context(OperationCommand::Put, Some(&mut operand));
+        assert!(matches!(header.merge_node_value(&mut context), Ok(ReturnCode::OK)));
+        assert_eq!(header.read_node_value(), Some(NodeValue { v: 8 }));
+
+        let mut operand: NodeValue = NodeValue { v: 34 };
+        let mut context: OperationContext = operation_context(OperationCommand::Put, Some(&mut operand));
+        assert!(matches!(header.merge_node_value(&mut context), Ok(ReturnCode::OK)));
+        assert_eq!(header.read_node_value(), Some(NodeValue { v: 42 }));
+
+        let mut operand: NodeValue = NodeValue { v: 8 };
+        let mut context: OperationContext = operation_context(OperationCommand::Put, Some(&mut operand));
+        context.header.set_strict_inserts(1);
+        assert!(matches!(header.merge_node_value(&mut context), Ok(ReturnCode::OK)));
+        assert_eq!(context.old_value, Some(NodeValue { v: 42 }));
+        assert_eq!(header.read_node_value(), Some(NodeValue { v: 50 }));
+    }
+
+    #[test]
+    fn test_check_delete_condition() {
+        let mut node: [u8; 16] = [0; 16];
+        node[0] = TopNode::new().with_type_flag(NodeType::LeafNodeWithValue).with_delta(1).into_bits();
+        let header: &NodeHeader = unsafe { &*(node.as_ptr() as *const NodeHeader) };
+        let offset: usize = header.get_offset_node_value();
+        node[offset..offset + 8].copy_from_slice(&42u64.to_ne_bytes());
+        let header: &NodeHeader = unsafe { &*(node.as_ptr() as *const NodeHeader) };
+
+        let mut context: OperationContext = operation_context(OperationCommand::Delete, None);
+        assert!(matches!(header.check_delete_condition(&mut context), ReturnCode::OK));
+        assert_eq!(context.old_value, Some(NodeValue { v: 42 }));
+
+        context.old_value = None;
+        context.expected_value = Some(NodeValue { v: 42 });
+        assert!(matches!(header.check_delete_condition(&mut context), ReturnCode::OK));
+        assert_eq!(context.old_value, Some(NodeValue { v: 42 }));
+
+        context.old_value = None;
+        context.expected_value = Some(NodeValue { v: 7 });
+        assert!(matches!(header.check_delete_condition(&mut context), ReturnCode::DeleteFailureValueMismatch));
+        assert_eq!(context.old_value, None);
+    }
+
+    #[test]
+    fn test_compare_and_swap_node_value() {
+        let mut node: [u8; 16] = [0; 16];
+        node[0] = TopNode::new().with_type_flag(NodeType::LeafNodeEmpty).with_delta(1).into_bits();
+        let header: &mut NodeHeader = unsafe { &mut *(node.as_mut_ptr() as *mut NodeHeader) };
+
+        let mut value: NodeValue = NodeValue { v: 42 };
+        let mut context: OperationContext = operation_context(OperationCommand::Put, Some(&mut value));
+        assert!(matches!(header.compare_and_swap_node_value(&mut context), Ok(ReturnCode::PutFailureValueaddNeedsShift)));
+
+        header.as_top_node_mut().set_type_flag(NodeType::LeafNodeWithValue);
+        header.write_node_value(NodeValue { v: 7 });
+        context.expected_value = Some(NodeValue { v: 8 });
+        assert!(matches!(header.compare_and_swap_node_value(&mut context), Ok(ReturnCode::CompareAndSwapFailureValueMismatch)));
+        assert_eq!(context.header.operation_done(), 1);
+        assert_eq!(context.old_value, Some(NodeValue { v: 7 }));
+        assert_eq!(header.read_node_value(), Some(NodeValue { v: 7 }));
+
+        context.expected_value = Some(NodeValue { v: 7 });
+        assert!(matches!(header.compare_and_swap_node_value(&mut context), Ok(ReturnCode::OK)));
+        assert_eq!(header.read_node_value(), Some(NodeValue { v: 42 }));
+    }
+
+    #[test]
+    fn test_fetch_add_node_value() {
+        let mut node: [u8; 16] = [0; 16];
+        node[0] = TopNode::new().with_type_flag(NodeType::LeafNodeWithValue).with_delta(1).into_bits();
+        let header: &mut NodeHeader = unsafe { &mut *(node.as_mut_ptr() as *mut NodeHeader) };
+        header.write_node_value(NodeValue { v: u64::MAX });
+
+        let mut delta: NodeValue = NodeValue { v: 3 };
+        let mut context: OperationContext = operation_context(OperationCommand::Put, Some(&mut delta));
+        assert!(matches!(header.fetch_add_node_value(&mut context), Ok(ReturnCode::OK)));
+        assert_eq!(context.old_value, Some(NodeValue { v: u64::MAX }));
+        assert_eq!(header.read_node_value(), Some(NodeValue { v: 2 }));
+        assert_eq!(delta, NodeValue { v: 2 });
+
+        node[0] = TopNode::new().with_type_flag(NodeType::InnerNode).with_delta(1).into_bits();
+        let header: &mut NodeHeader = unsafe { &mut *(node.as_mut_ptr() as *mut NodeHeader) };
+        let mut delta: NodeValue = NodeValue { v: 3 };
+        let mut context: OperationContext = operation_context(OperationCommand::Put, Some(&mut delta));
+        assert!(matches!(header.fetch_add_node_value(&mut context), Ok(ReturnCode::GetFailureNoLeaf)));
+    }
+
+    #[test]
+    fn test_call_top_node_suffix() {
+        fn check_suffix(key: &mut Atomicu8, key_len: u16, _value: &mut AtomicNodeValue) -> bool {
+            let suffix: &[u8] = unsafe { std::slice::from_raw_parts(key.get(), key_len as usize) };
+            suffix == b"cd"
+        }
+
+        let mut node: [u8; 16] = [0; 16];
+        node[0] = TopNode::new().with_type_flag(NodeType::LeafNodeEmpty).with_delta(1).into_bits();
+        let header: &mut NodeHeader = unsafe { &mut *(node.as_mut_ptr() as *mut NodeHeader) };
+
+        let mut key: [u8; 4] = *b"abcd";
+        let mut arena: AtomicArena = AtomicArena::new();
+        let mut range_query_context: RangeQueryContext = RangeQueryContext {
+            key_begin: AtomicChar::new(),
+            current_key: Atomicu8::new_from_pointer(key.as_mut_ptr()),
+            arena: &mut arena,
+            current_stack_depth: 0,
+            current_key_offset: 3,
+            key_len: 2,
+            suffix_offset: 2,
+            do_report: 0,
+            stack: [const { None }; 128]
+        };
+        assert!(header.call_top_node(&mut range_query_context, check_suffix));
+
+        range_query_context.suffix_offset = 0;
+        assert!(!header.call_top_node(&mut range_query_context, check_suffix));
+    }
 }
@@ -1,3 +1,5 @@
+use crate::hyperion::components::error::HyperionError;
+
 pub enum ReturnCode {
     OK,
     KeyNotFound,
@@ -16,9 +18,63 @@ pub enum ReturnCode {
     PutFailureExpandFailed,
     PutFailureValueaddNeedsShift,
     PutFailureKeyaddNeedsShift,
+    PutFailureKeyExists,
+    PutFailureBackpressure,
     GetFailureTraverse,
     GetFailureNoNode,
     GetFailureNoLeaf,
+    DeleteFailureValueMismatch,
+    CompareAndSwapFailureValueMismatch,
     UnknownOperation,
     INITIAL,
 }
+
+impl ReturnCode {
+    /// Maps this return code to the outcome reported by the public API.
+    ///
+    /// Returns `Ok`, if the operation succeeded.
+    /// Returns the matching `HyperionError`, otherwise. Codes steering the traversal internally, e.g. `ExpandingNecessary`, are reported
+    /// as `InvariantViolation`, as they must never leave an operation.
+    pub fn into_result(self) -> Result<(), HyperionError> {
+        match self {
+            ReturnCode::OK => Ok(()),
+            ReturnCode::KeyNotFound | ReturnCode::GetFailureNoNode | ReturnCode::GetFailureNoLeaf => Err(HyperionError::KeyNotFound),
+            ReturnCode::PutFailureKeyExists => Err(HyperionError::KeyExists),
+            ReturnCode::DeleteFailureValueMismatch | ReturnCode::CompareAndSwapFailureValueMismatch => Err(HyperionError::ValueMismatch),
+            ReturnCode::PutFailureBackpressure => Err(HyperionError::Backpressure),
+            ReturnCode::ExpandingContainerFailed | ReturnCode::ExpandingCallocFailed | ReturnCode::PutFailureExpandFailed => {
+                Err(HyperionError::AllocationFailed)
+            },
+            ReturnCode::ContainerInvalidSize => Err(HyperionError::Corruption("invalid container size")),
+            ReturnCode::ChildContainerMissing => Err(HyperionError::Corruption("child container is missing")),
+            ReturnCode::PointerOutOfContainerBound => Err(HyperionError::Corruption("pointer exceeds its container")),
+            ReturnCode::PointerNull | ReturnCode::PointerInvalid => Err(HyperionError::Corruption("invalid pointer")),
+            ReturnCode::UnknownOperation => Err(HyperionError::InvariantViolation("unknown operation")),
+            ReturnCode::BLANK
+            | ReturnCode::ShiftFailure
+            | ReturnCode::ExpandingNecessary
+            | ReturnCode::AddValueFailure
+            | ReturnCode::PutFailureValueaddNeedsShift
+            | ReturnCode::PutFailureKeyaddNeedsShift
+            | ReturnCode::GetFailureTraverse
+            | ReturnCode::INITIAL => Err(HyperionError::InvariantViolation("internal return code left the operation"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod return_codes_test {
+    use crate::hyperion::components::error::HyperionError;
+    use crate::hyperion::components::return_codes::ReturnCode;
+
+    #[test]
+    fn test_into_result() {
+        assert_eq!(ReturnCode::OK.into_result(), Ok(()));
+        assert_eq!(ReturnCode::GetFailureNoLeaf.into_result(), Err(HyperionError::KeyNotFound));
+        assert_eq!(ReturnCode::PutFailureKeyExists.into_result(), Err(HyperionError::KeyExists));
+        assert_eq!(ReturnCode::CompareAndSwapFailureValueMismatch.into_result(), Err(HyperionError::ValueMismatch));
+        assert_eq!(ReturnCode::PutFailureBackpressure.into_result(), Err(HyperionError::Backpressure));
+        assert_eq!(ReturnCode::ExpandingCallocFailed.into_result(), Err(HyperionError::AllocationFailed));
+        assert!(matches!(ReturnCode::ExpandingNecessary.into_result(), Err(HyperionError::InvariantViolation(_))));
+    }
+}
@@ -1,5 +1,6 @@
 use bitfield_struct::bitfield;
 
+use crate::hyperion::components::error::HyperionError;
 use crate::hyperion::components::node::NodeType;
 
 #[derive(Debug)]
@@ -18,6 +19,9 @@ impl ChildLinkType {
 
     /// Transforms its states from an 8 bit value into a named state.
     ///
+    /// Reserved for internal constants and bitfield accessors, which cannot produce undefined values. Values read from persisted or
+    /// raw memory are decoded with [`ChildLinkType::try_from_bits`] instead.
+    ///
     /// # Panics
     /// Panics if an invalid link type was found.
     pub(crate) const fn from_bits(value: u8) -> Self {
@@ -29,6 +33,16 @@ impl ChildLinkType {
             _ => panic!("Use of undefined link type")
         }
     }
+
+    /// Transforms its states from an 8 bit value into a named state.
+    ///
+    /// Returns `HyperionError::Corruption`, if the value does not name a link type.
+    pub fn try_from_bits(value: u8) -> Result<Self, HyperionError> {
+        match value {
+            0..=3 => Ok(Self::from_bits(value)),
+            _ => Err(HyperionError::Corruption("undefined link type"))
+        }
+    }
 }
 
 #[bitfield(u8, order = Msb)]
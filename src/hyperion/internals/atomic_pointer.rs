@@ -5,6 +5,8 @@ use std::sync::atomic::{AtomicPtr, Ordering};
 
 use crate::hyperion::components::container::{Container, EmbeddedContainer, RootContainerEntry};
 use crate::hyperion::components::context::PathCompressedEjectionContext;
+use crate::hyperion::components::error::HyperionError;
+use crate::hyperion::components::error::HyperionError::InvariantViolation;
 use crate::hyperion::components::node::NodeValue;
 use crate::hyperion::components::node_header::NodeHeader;
 use crate::memorymanager::api::{get_pointer, malloc, Arena, HyperionPointer};
@@ -61,8 +63,15 @@ impl<T> AtomicPointer<T> {
         self.get() as *mut c_void
     }
 
-    pub fn borrow_mut(&mut self) -> &mut T {
-        unsafe { self.get().as_mut().unwrap() }
+    /// Borrows the pointee mutably.
+    ///
+    /// Returns `InvariantViolation`, if the pointer is null.
+    ///
+    /// # Panics
+    /// Panics in debug builds, if the pointer does not satisfy the alignment of `T`, e.g. when it points into a packed container.
+    pub fn borrow_mut(&mut self) -> Result<&mut T, HyperionError> {
+        debug_assert!(self.get().is_aligned(), "Misaligned AtomicPointer: {:p}", self.get());
+        unsafe { self.get().as_mut() }.ok_or(InvariantViolation("AtomicPointer is null"))
     }
 }
 
@@ -79,12 +88,12 @@ pub type AtomicNodeValue = AtomicPointer<NodeValue>;
 
 pub const CONTAINER_SIZE_TYPE_0: usize = 32;
 
-pub fn initialize_container(arena: &mut AtomicArena) -> HyperionPointer {
-    let mut container_pointer: HyperionPointer = malloc(arena.borrow_mut(), CONTAINER_SIZE_TYPE_0);
+pub fn initialize_container(arena: &mut AtomicArena) -> Result<HyperionPointer, HyperionError> {
+    let mut container_pointer: HyperionPointer = malloc(arena.borrow_mut()?, CONTAINER_SIZE_TYPE_0);
     let mut container: AtomicContainer =
-        AtomicContainer::new_from_pointer(get_pointer(arena.borrow_mut(), &mut container_pointer, 1, 0) as *mut Container);
-    container.borrow_mut().set_size(CONTAINER_SIZE_TYPE_0 as u32);
-    let container_head_size: i32 = container.borrow_mut().get_container_head_size();
-    container.borrow_mut().set_free_size_left((CONTAINER_SIZE_TYPE_0 as i32 - container_head_size) as u32);
-    container_pointer
+        AtomicContainer::new_from_pointer(get_pointer(arena.borrow_mut()?, &mut container_pointer, 1, 0) as *mut Container);
+    container.borrow_mut()?.set_size(CONTAINER_SIZE_TYPE_0 as u32);
+    let container_head_size: i32 = container.borrow_mut()?.get_container_head_size();
+    container.borrow_mut()?.set_free_size_left((CONTAINER_SIZE_TYPE_0 as i32 - container_head_size) as u32);
+    Ok(container_pointer)
 }
@@ -1,13 +1,83 @@
-use std::sync::atomic::AtomicPtr;
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
 
 use bitfield_struct::bitfield;
 
+use crate::hyperion::components::container::{CONTAINER_MAX_EMBEDDED_SIZE, CONTAINER_MAX_FREESIZE, CONTAINER_MAX_SIZE};
+use crate::hyperion::components::node::NodeValue;
 use crate::hyperion::internals::atomic_pointer::{AtomicPointer, Atomicu8};
 use crate::hyperion::preprocessor::key_preprocessor::KeyProcessingIDs;
-use crate::memorymanager::api::AtomicMemoryPointer;
+use crate::memorymanager::api::{reserved_memory, AtomicMemoryPointer};
 
 pub type HyperionCallback<T> = fn(key: &mut Atomicu8, key_len: u16, value: &mut AtomicPointer<T>) -> bool;
+/// Combines the value currently stored for a key, if any, with a merge operand into the value to be stored.
+pub type MergeFn = fn(old: Option<&NodeValue>, operand: &NodeValue) -> NodeValue;
+/// Notified with `true` when load shedding starts and with `false` when it stops.
+pub type LoadSheddingFn = fn(active: bool);
+/// Returns the size a container of `current_size` bytes grows to, if it has to provide `required_minimum` additional bytes.
+pub type GrowthFn = fn(current_size: u32, required_minimum: u32, increment: u32) -> u32;
+
+/// Set while puts of new keys are rejected due to memory pressure.
+static LOAD_SHEDDING: AtomicBool = AtomicBool::new(false);
+
+/// Decides when an embedded container is ejected into a standalone container.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum EmbeddingPolicy {
+    /// Eject once the embedded container would outgrow `container_embedding_limit` bytes.
+    SizeBased = 0,
+    /// Eject as soon as an embedded container needs to grow.
+    EagerEject = 1,
+    /// Keep embedding until the embedded container header cannot represent the size anymore.
+    MaxEmbed = 2
+}
+
+impl EmbeddingPolicy {
+    /// Transforms its states into a 2 bit representation.
+    pub(crate) const fn into_bits(self) -> u8 {
+        self as _
+    }
+
+    /// Transforms its states from an 8 bit value into a named state.
+    ///
+    /// # Panics
+    /// Panics if an invalid embedding policy was found.
+    pub(crate) const fn from_bits(value: u8) -> Self {
+        match value {
+            0 => EmbeddingPolicy::SizeBased,
+            1 => EmbeddingPolicy::EagerEject,
+            2 => EmbeddingPolicy::MaxEmbed,
+            _ => panic!("Use of undefined embedding policy")
+        }
+    }
+}
+
+/// Decides how much a container grows once it runs out of free bytes.
+///
+/// Every policy grows at least by the smallest multiple of `container_size_increment` providing the required bytes and at most up to
+/// the container size cap.
+#[derive(Debug, Copy, Clone)]
+pub enum GrowthPolicy {
+    /// Grows by the smallest multiple of the increment providing the required bytes, which keeps containers tight.
+    Linear,
+    /// Grows by at least `percent` percent of the current size, rounded up to the increment, which reduces reallocations of write-heavy
+    /// containers.
+    Exponential { percent: u32 },
+    /// Lets the given function decide the new size.
+    Custom(GrowthFn)
+}
+
+/// Decides how much trailing free space containers keep for future writes.
+///
+/// Keeping slack avoids reallocations on subsequent writes, while trimming returns memory to the arena early.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct FreeSpacePolicy {
+    /// Trailing free bytes a container keeps when it is trimmed, bounded by `CONTAINER_MAX_FREESIZE`.
+    pub max_trailing_free: u32,
+    /// Trims a container after an embedded container was ejected from it.
+    pub trim_on_eject: bool,
+    /// Trims a container after a key was deleted from it.
+    pub trim_on_delete: bool
+}
 
 #[bitfield(u64, order = Msb)]
 pub struct GlobalConfigurationHeader {
@@ -23,14 +93,38 @@ pub struct GlobalConfigurationHeader {
     pub io_threads: u16,
     #[bits(32)]
     pub container_embedding_high_watermark: u32,
-    #[bits(4)]
-    __: u8
+    #[bits(2)]
+    pub embedding_policy: EmbeddingPolicy,
+    #[bits(1)]
+    __: u8,
+    /// Lets reads eject embedded containers exceeding the embedding ceiling, if set to `1`.
+    #[bits(1)]
+    pub eject_on_read: u8
 }
 
 pub struct GlobalConfiguration {
     pub header: GlobalConfigurationHeader,
     pub top_level_successor_threshold: u32,
+    /// Number of top nodes per jump table block of a container, `0` disables container jump tables.
+    pub container_jump_table_threshold: u32,
+    /// Size in bytes beyond which embedded containers are ejected under `EmbeddingPolicy::SizeBased`, bounded by
+    /// `CONTAINER_MAX_EMBEDDED_SIZE`.
     pub container_embedding_limit: u32,
+    /// Size in bytes beyond which writes split a container instead of growing it, bounded by `CONTAINER_MAX_SIZE`.
+    pub container_size_cap: u32,
+    /// Growth applied to containers running out of free bytes.
+    pub container_growth_policy: GrowthPolicy,
+    /// Trailing free space kept by containers after ejections and deletes.
+    pub free_space_policy: FreeSpacePolicy,
+    /// Merge operator applied by merges at the leaf, `None` until one is registered.
+    pub merge_operator: Option<MergeFn>,
+    /// Reserved arena memory in bytes from which puts of new keys are rejected, `0` disables load shedding.
+    pub load_shedding_high_watermark: usize,
+    /// Reserved arena memory in bytes below which load shedding stops again, `0` or a value above the high watermark stop it below the
+    /// high watermark.
+    pub load_shedding_low_watermark: usize,
+    /// Callback notified when load shedding starts or stops.
+    pub load_shedding_callback: Option<LoadSheddingFn>,
     pub num_writes_million: i64,
     pub num_reads_million: i64
 }
@@ -42,9 +136,79 @@ pub static mut GLOBAL_CONFIG: Mutex<GlobalConfiguration> = Mutex::new(GlobalConf
         .with_preprocessor_strategy(KeyProcessingIDs::None)
         .with_container_size_increment(32)
         .with_io_threads(1)
-        .with_container_embedding_high_watermark(0),
+        .with_container_embedding_high_watermark(0)
+        .with_embedding_policy(EmbeddingPolicy::SizeBased)
+        .with_eject_on_read(0),
     top_level_successor_threshold: 0,
-    container_embedding_limit: 0,
+    container_jump_table_threshold: 32,
+    container_embedding_limit: CONTAINER_MAX_EMBEDDED_SIZE as u32,
+    container_size_cap: CONTAINER_MAX_SIZE,
+    container_growth_policy: GrowthPolicy::Linear,
+    free_space_policy: FreeSpacePolicy {
+        max_trailing_free: CONTAINER_MAX_FREESIZE,
+        trim_on_eject: true,
+        trim_on_delete: false
+    },
+    merge_operator: None,
+    load_shedding_high_watermark: 0,
+    load_shedding_low_watermark: 0,
+    load_shedding_callback: None,
     num_writes_million: 0,
     num_reads_million: 0
 });
+
+/// Locks the global configuration.
+///
+/// The configuration holds plain values only, so a lock poisoned by a panicking thread is recovered instead of propagating the panic.
+pub fn global_config() -> MutexGuard<'static, GlobalConfiguration> {
+    unsafe { GLOBAL_CONFIG.lock().unwrap_or_else(PoisonError::into_inner) }
+}
+
+/// Serializes tests that read or write the global configuration, as the test harness runs them in parallel threads sharing it.
+#[cfg(test)]
+pub(crate) static CONFIG_LOCK: Mutex<()> = Mutex::new(());
+
+/// Returns `true`, if puts of new keys are currently rejected due to memory pressure.
+/// Returns `false`, otherwise.
+pub fn is_load_shedding() -> bool {
+    LOAD_SHEDDING.load(Ordering::SeqCst)
+}
+
+/// Updates the load shedding state for the given amount of reserved arena memory and notifies the registered callback on changes.
+///
+/// Load shedding starts once `reserved_bytes` reaches the high watermark and stops once it drops below the low watermark. A low
+/// watermark of `0` or above the high watermark acts like the high watermark.
+/// Returns `true`, if load shedding is active afterwards.
+/// Returns `false`, otherwise.
+pub fn update_load_shedding(reserved_bytes: usize) -> bool {
+    let (active, callback): (bool, Option<LoadSheddingFn>) = {
+        let config: MutexGuard<GlobalConfiguration> = global_config();
+        let high_watermark: usize = config.load_shedding_high_watermark;
+        let low_watermark: usize = match config.load_shedding_low_watermark {
+            0 => high_watermark,
+            low_watermark => low_watermark.min(high_watermark)
+        };
+        let active: bool = match high_watermark {
+            0 => false,
+            _ if is_load_shedding() => reserved_bytes >= low_watermark,
+            _ => reserved_bytes >= high_watermark
+        };
+        (active, config.load_shedding_callback)
+    };
+
+    if LOAD_SHEDDING.swap(active, Ordering::SeqCst) != active {
+        if let Some(callback) = callback {
+            callback(active);
+        }
+    }
+    active
+}
+
+/// Updates the load shedding state for the memory currently reserved by all arenas.
+///
+/// Sums the bins of all arenas, so it is meant to be called periodically rather than on every put. The memory manager does not depend
+/// on this module and never calls it, so the owner of the arenas has to call it, e.g. after every batch of writes, for the watermarks
+/// to take effect.
+pub fn refresh_load_shedding() -> bool {
+    update_load_shedding(reserved_memory())
+}
@@ -1,3 +1,5 @@
+#![cfg_attr(not(test), deny(clippy::unwrap_used))]
+
 pub mod atomic_pointer;
 pub mod core;
 pub mod helpers;
@@ -3,4 +3,10 @@ pub mod api;
 pub mod components;
 pub mod globals;
 pub mod internals;
+pub mod plugin;
 mod preprocessor;
+pub mod selftest;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
+pub use selftest::selftest;
@@ -0,0 +1,151 @@
+//! Entry points for loading the crate as a `cdylib` plugin from Rust host applications.
+//!
+//! Rust types have no stable layout across compiler and crate versions, so a host linking several versions of this crate at runtime
+//! only exchanges the `#[repr(C)]` types of this module. The host resolves `hyperion_vtable`, checks `abi_version` and `size`, and calls
+//! every other function through the returned table.
+//!
+//! The table covers the lifecycle and maintenance of a trie so far. Puts, gets, deletes and range queries are appended once the traversal
+//! engine provides them, which keeps tables of older plugins readable up to their `size`.
+
+use std::ffi::c_void;
+use std::ptr::null_mut;
+
+use crate::hyperion::components::container::{RootContainerEntry, RootContainerStats};
+use crate::hyperion::internals::atomic_pointer::{initialize_container, AtomicArena};
+use crate::memorymanager::api::{Arena, HyperionPointer};
+
+/// Version of the vtable layout, incremented whenever existing entries change.
+pub const HYPERION_ABI_VERSION: u32 = 1;
+
+/// Opaque, pointer-sized handle of a trie owned by the plugin that created it.
+///
+/// A handle must only be passed to the vtable of the plugin that created it.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct HyperionHandle {
+    trie: *mut c_void
+}
+
+impl HyperionHandle {
+    /// Returns `true`, if the handle does not refer to a trie.
+    /// Returns `false`, otherwise.
+    pub fn is_null(&self) -> bool {
+        self.trie.is_null()
+    }
+}
+
+/// Function table of a plugin.
+///
+/// Entries are only ever appended, so hosts read the entries covered by `size` and treat all others as unavailable.
+#[repr(C)]
+pub struct HyperionVTable {
+    /// Layout version of this table, see `HYPERION_ABI_VERSION`.
+    pub abi_version: u32,
+    /// Size of this table in bytes.
+    pub size: u32,
+    /// Creates an empty trie, returns a null handle if the root container cannot be allocated.
+    pub create: extern "C" fn() -> HyperionHandle,
+    /// Frees the trie and all memory of its arena, the handle must not be used afterwards.
+    pub destroy: extern "C" fn(handle: HyperionHandle),
+    /// Returns the bytes reserved by the arena of the trie.
+    pub reserved_bytes: extern "C" fn(handle: HyperionHandle) -> usize,
+    /// Shrinks the root container to its used bytes and returns the number of released bytes.
+    pub shrink_to_fit: extern "C" fn(handle: HyperionHandle) -> usize
+}
+
+/// Trie behind a `HyperionHandle`.
+///
+/// The arena is boxed, as the root container entry keeps a pointer to it.
+struct PluginTrie {
+    arena: Box<Arena>,
+    root: RootContainerEntry
+}
+
+static HYPERION_VTABLE: HyperionVTable = HyperionVTable {
+    abi_version: HYPERION_ABI_VERSION,
+    size: size_of::<HyperionVTable>() as u32,
+    create: plugin_create,
+    destroy: plugin_destroy,
+    reserved_bytes: plugin_reserved_bytes,
+    shrink_to_fit: plugin_shrink_to_fit
+};
+
+/// Returns the function table of this plugin.
+#[no_mangle]
+pub extern "C" fn hyperion_vtable() -> &'static HyperionVTable {
+    &HYPERION_VTABLE
+}
+
+/// Returns the trie behind the handle, if any.
+fn trie_of(handle: HyperionHandle) -> Option<&'static mut PluginTrie> {
+    unsafe { (handle.trie as *mut PluginTrie).as_mut() }
+}
+
+extern "C" fn plugin_create() -> HyperionHandle {
+    let mut arena: Box<Arena> = Box::default();
+    let mut atomic_arena: AtomicArena = AtomicArena::new_from_pointer(arena.as_mut());
+    let hyperion_pointer: HyperionPointer = match initialize_container(&mut atomic_arena) {
+        Ok(hyperion_pointer) => hyperion_pointer,
+        Err(_) => {
+            arena.lock().teardown_all_superbins();
+            return HyperionHandle { trie: null_mut() };
+        }
+    };
+    let trie: Box<PluginTrie> = Box::new(PluginTrie {
+        arena,
+        root: RootContainerEntry {
+            spinlock: 0,
+            stats: RootContainerStats {
+                puts: 0,
+                gets: 0,
+                updates: 0,
+                range_queries: 0,
+                write_lock: 0
+            },
+            arena: atomic_arena,
+            hyperion_pointer
+        }
+    });
+    HyperionHandle { trie: Box::into_raw(trie) as *mut c_void }
+}
+
+extern "C" fn plugin_destroy(handle: HyperionHandle) {
+    if !handle.is_null() {
+        let mut trie: Box<PluginTrie> = unsafe { Box::from_raw(handle.trie as *mut PluginTrie) };
+        trie.arena.lock().teardown_all_superbins();
+    }
+}
+
+extern "C" fn plugin_reserved_bytes(handle: HyperionHandle) -> usize {
+    trie_of(handle).map_or(0, |trie: &mut PluginTrie| trie.arena.reserved_bytes())
+}
+
+extern "C" fn plugin_shrink_to_fit(handle: HyperionHandle) -> usize {
+    trie_of(handle).map_or(0, |trie: &mut PluginTrie| trie.root.shrink_to_fit().unwrap_or(0))
+}
+
+#[cfg(test)]
+mod plugin_test {
+    use crate::hyperion::internals::core::CONFIG_LOCK;
+    use crate::hyperion::plugin::{hyperion_vtable, HyperionHandle, HyperionVTable, HYPERION_ABI_VERSION};
+
+    #[test]
+    fn test_handle_layout() {
+        assert_eq!(size_of::<HyperionHandle>(), size_of::<usize>());
+        assert_eq!(align_of::<HyperionHandle>(), align_of::<usize>());
+    }
+
+    #[test]
+    fn test_vtable_lifecycle() {
+        let _config_lock = CONFIG_LOCK.lock();
+        let vtable: &HyperionVTable = hyperion_vtable();
+        assert_eq!(vtable.abi_version, HYPERION_ABI_VERSION);
+        assert_eq!(vtable.size as usize, size_of::<HyperionVTable>());
+
+        let handle: HyperionHandle = (vtable.create)();
+        assert!(!handle.is_null());
+        assert!((vtable.reserved_bytes)(handle) > 0);
+        assert_eq!((vtable.shrink_to_fit)(handle), 0);
+        (vtable.destroy)(handle);
+    }
+}
@@ -0,0 +1,112 @@
+//! Validates the platform assumptions baked into the trie and memory manager layout.
+//!
+//! Containers are addressed byte-wise and the sizes of all headers are part of the offset arithmetic, so a platform that lays out any
+//! of these types differently would silently corrupt data instead of failing.
+
+use std::fmt::{Display, Formatter};
+
+use crate::hyperion::components::container::{Container, ContainerLink, EmbeddedContainer};
+use crate::hyperion::components::jump_table::{SubNodeJumpTableEntry, TopNodeJumpTable};
+use crate::hyperion::components::node::{NodeType, NodeValue};
+use crate::hyperion::components::node_header::{NodeHeader, PathCompressedNodeHeader};
+use crate::hyperion::components::top_node::TopNode;
+use crate::memorymanager::api::{ExtendedHyperionPointer, HyperionPointer};
+
+/// Outcome of validating a single layout assumption.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfTestCheck {
+    pub name: &'static str,
+    pub expected: usize,
+    pub actual: usize
+}
+
+impl SelfTestCheck {
+    /// Returns `true`, if the platform matches the assumption.
+    /// Returns `false`, otherwise.
+    pub fn passed(&self) -> bool {
+        self.expected == self.actual
+    }
+}
+
+/// Outcome of all layout assumptions validated by [`selftest`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfTestReport {
+    pub checks: Vec<SelfTestCheck>
+}
+
+impl SelfTestReport {
+    /// Returns `true`, if all assumptions hold on this platform.
+    /// Returns `false`, otherwise.
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(SelfTestCheck::passed)
+    }
+
+    /// Returns all assumptions that do not hold on this platform.
+    pub fn failures(&self) -> impl Iterator<Item = &SelfTestCheck> {
+        self.checks.iter().filter(|check: &&SelfTestCheck| !check.passed())
+    }
+}
+
+impl Display for SelfTestReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for check in &self.checks {
+            let status: &str = if check.passed() { "ok" } else { "FAILED" };
+            writeln!(f, "{status:>6} {}: expected {}, found {}", check.name, check.expected, check.actual)?;
+        }
+        Ok(())
+    }
+}
+
+fn check(name: &'static str, expected: usize, actual: usize) -> SelfTestCheck {
+    SelfTestCheck { name, expected, actual }
+}
+
+/// Validates the type sizes, bitfield ordering, pointer width and endianness the layout relies on.
+///
+/// Meant to run once before the first trie is created, so that unsupported platforms fail fast. Returns a report listing every check.
+pub fn selftest() -> SelfTestReport {
+    SelfTestReport {
+        checks: vec![
+            check("size_of::<NodeHeader>()", 1, size_of::<NodeHeader>()),
+            check("size_of::<PathCompressedNodeHeader>()", 1, size_of::<PathCompressedNodeHeader>()),
+            check("size_of::<EmbeddedContainer>()", 1, size_of::<EmbeddedContainer>()),
+            check("size_of::<Container>()", 4, size_of::<Container>()),
+            check("size_of::<ContainerLink>()", 8, size_of::<ContainerLink>()),
+            check("size_of::<SubNodeJumpTableEntry>()", 4, size_of::<SubNodeJumpTableEntry>()),
+            check("size_of::<TopNodeJumpTable>()", 30, size_of::<TopNodeJumpTable>()),
+            check("size_of::<NodeValue>()", 8, size_of::<NodeValue>()),
+            check("size_of::<HyperionPointer>()", 5, size_of::<HyperionPointer>()),
+            check("size_of::<ExtendedHyperionPointer>()", 16, size_of::<ExtendedHyperionPointer>()),
+            check(
+                "TopNode type flag in the most significant bits",
+                0b1100_0000,
+                TopNode::new().with_type_flag(NodeType::LeafNodeWithValue).into_bits() as usize
+            ),
+            check("Container size in the most significant bits", 1 << 13, Container::new().with_size(1).into_bits() as usize),
+            check("size_of::<usize>()", 8, size_of::<usize>()),
+            check("little endian", 1, cfg!(target_endian = "little") as usize),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod selftest_test {
+    use crate::hyperion::selftest::{selftest, SelfTestCheck, SelfTestReport};
+
+    #[test]
+    fn test_selftest() {
+        let report: SelfTestReport = selftest();
+        assert!(report.passed(), "{report}");
+        assert_eq!(report.failures().count(), 0);
+
+        let failing: SelfTestReport = SelfTestReport {
+            checks: vec![SelfTestCheck {
+                name: "size_of::<NodeHeader>()",
+                expected: 2,
+                actual: 1
+            }]
+        };
+        assert!(!failing.passed());
+        assert_eq!(failing.to_string(), "FAILED size_of::<NodeHeader>(): expected 2, found 1\n");
+    }
+}
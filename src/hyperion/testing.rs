@@ -0,0 +1,96 @@
+//! Helpers for tests asserting which parts of an arena an operation modified.
+//!
+//! An `ArenaFingerprint` captures a content hash per bin. Comparing two fingerprints reveals writes that leaked into bins holding
+//! unrelated containers, e.g. due to offset bugs.
+
+use std::collections::BTreeMap;
+
+use crate::memorymanager::api::{Arena, HyperionPointer};
+
+/// Location of a bin within an arena.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BinLocation {
+    pub superbin_id: u8,
+    pub metabin_id: u16,
+    pub bin_id: u8
+}
+
+impl BinLocation {
+    /// Returns the location of the bin holding the chunk referenced by the given pointer.
+    pub fn of(hyperion_pointer: &HyperionPointer) -> Self {
+        BinLocation {
+            superbin_id: hyperion_pointer.superbin_id(),
+            metabin_id: hyperion_pointer.metabin_id(),
+            bin_id: hyperion_pointer.bin_id()
+        }
+    }
+}
+
+/// Content hashes of all allocated bins of an arena, captured at one point in time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArenaFingerprint {
+    bins: BTreeMap<BinLocation, u64>
+}
+
+impl ArenaFingerprint {
+    /// Hashes the usage mask and the chunks of every allocated bin of the given arena.
+    pub fn capture(arena: &mut Arena) -> Self {
+        ArenaFingerprint {
+            bins: arena
+                .bin_content_hashes()
+                .iter()
+                .map(|(hyperion_pointer, hash): &(HyperionPointer, u64)| (BinLocation::of(hyperion_pointer), *hash))
+                .collect()
+        }
+    }
+
+    /// Returns the locations of all bins that were allocated, released or modified between this fingerprint and `other`, in
+    /// ascending order.
+    pub fn changed_bins(&self, other: &ArenaFingerprint) -> Vec<BinLocation> {
+        let mut changed: Vec<BinLocation> = self
+            .bins
+            .iter()
+            .filter(|(location, hash): &(&BinLocation, &u64)| other.bins.get(location) != Some(hash))
+            .map(|(location, _): (&BinLocation, &u64)| *location)
+            .collect();
+        changed.extend(other.bins.keys().filter(|location: &&BinLocation| !self.bins.contains_key(location)));
+        changed.sort();
+        changed
+    }
+
+    /// Asserts that the given arena only changed in bins holding one of the `allowed` pointers since this fingerprint was captured.
+    ///
+    /// # Panics
+    /// Panics and lists the offending bins, if any other bin was allocated, released or modified.
+    pub fn assert_unchanged_except(&self, arena: &mut Arena, allowed: &[HyperionPointer]) {
+        let allowed: Vec<BinLocation> = allowed.iter().map(BinLocation::of).collect();
+        let unexpected: Vec<BinLocation> =
+            self.changed_bins(&ArenaFingerprint::capture(arena)).into_iter().filter(|location: &BinLocation| !allowed.contains(location)).collect();
+        assert!(unexpected.is_empty(), "Unexpected modification of bins {unexpected:?}");
+    }
+}
+
+#[cfg(test)]
+mod testing_test {
+    use crate::hyperion::testing::{ArenaFingerprint, BinLocation};
+    use crate::memorymanager::api::{get_pointer, malloc, Arena, HyperionPointer};
+
+    #[test]
+    fn test_arena_fingerprint() {
+        let mut arena: Arena = Arena::default();
+        let mut first: HyperionPointer = malloc(&mut arena, 200);
+        let second: HyperionPointer = malloc(&mut arena, 2000);
+        let fingerprint: ArenaFingerprint = ArenaFingerprint::capture(&mut arena);
+        assert_eq!(fingerprint, ArenaFingerprint::capture(&mut arena));
+
+        unsafe { (get_pointer(&mut arena, &mut first, 0, 0) as *mut u8).write(0xAB) };
+        assert_eq!(fingerprint.changed_bins(&ArenaFingerprint::capture(&mut arena)), vec![BinLocation::of(&first)]);
+        fingerprint.assert_unchanged_except(&mut arena, &[first]);
+
+        let result: std::thread::Result<()> =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| fingerprint.assert_unchanged_except(&mut arena, &[second])));
+        assert!(result.is_err());
+
+        arena.lock().teardown_all_superbins();
+    }
+}
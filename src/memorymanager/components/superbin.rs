@@ -1,6 +1,6 @@
 use bitfield_struct::bitfield;
 
-use crate::memorymanager::components::bin::Bin;
+use crate::memorymanager::components::bin::{Bin, BIN_ELEMENTS};
 use crate::memorymanager::components::metabin::{Metabin, META_MAXMETABINS, META_RINGSIZE_EXT};
 use crate::memorymanager::internals::allocator::AllocatedBy;
 use crate::memorymanager::internals::simd_common::apply_sorted_insert;
@@ -33,6 +33,47 @@ pub(crate) struct SuperbinHeader {
     __: u16
 }
 
+/// Occupancy and fragmentation of the bins of a single superbin.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SuperbinFragmentation {
+    /// Id of the superbin, which equals its size class.
+    pub superbin_id: u8,
+    /// Size of a single chunk of this size class in bytes.
+    pub chunk_size: u16,
+    /// Number of bins holding allocated chunk memory.
+    pub bins_in_use: usize,
+    /// Number of used chunks over all bins.
+    pub used_chunks: usize,
+    /// Number of unused chunks over all bins.
+    pub free_chunks: usize,
+    /// Longest run of consecutive unused chunks found in any bin.
+    pub largest_free_run: usize,
+    /// `true`, if the superbin keeps a cached bin for future allocations.
+    pub has_cached_bin: bool
+}
+
+impl SuperbinFragmentation {
+    /// Returns the fragmentation score of this superbin in `[0.0, 1.0]`.
+    ///
+    /// A score of `0.0` means that all free chunks form a single contiguous run, a score close to `1.0` means that the free chunks are
+    /// scattered over many small gaps.
+    pub fn score(&self) -> f64 {
+        if self.free_chunks == 0 {
+            return 0.0;
+        }
+        1.0 - self.largest_free_run as f64 / self.free_chunks as f64
+    }
+
+    /// Returns the share of used chunks of all chunks held by this superbin in `[0.0, 1.0]`.
+    pub fn occupancy(&self) -> f64 {
+        let total_chunks: usize = self.used_chunks + self.free_chunks;
+        if total_chunks == 0 {
+            return 0.0;
+        }
+        self.used_chunks as f64 / total_chunks as f64
+    }
+}
+
 #[derive(Clone)]
 #[repr(C, align(64))]
 pub(crate) struct Superbin {
@@ -106,6 +147,32 @@ impl Superbin {
         true
     }
 
+    /// Collects the occupancy and fragmentation statistics of all bins of this superbin.
+    pub(crate) fn fragmentation(&self) -> SuperbinFragmentation {
+        let mut statistics: SuperbinFragmentation = SuperbinFragmentation {
+            superbin_id: self.header.superbin_id(),
+            chunk_size: self.header.size_of_bin(),
+            bins_in_use: 0,
+            used_chunks: 0,
+            free_chunks: 0,
+            largest_free_run: 0,
+            has_cached_bin: !self.bin_cache.is_null()
+        };
+
+        for i in 0..self.header.metabins_initialized() {
+            if let Some(metabin) = self.metabins.get(i as usize) {
+                for bin in metabin.bins.iter().filter(|bin: &&Bin| !bin.is_empty()) {
+                    let free_chunks: usize = bin.count_free_chunks();
+                    statistics.bins_in_use += 1;
+                    statistics.free_chunks += free_chunks;
+                    statistics.used_chunks += BIN_ELEMENTS - free_chunks;
+                    statistics.largest_free_run = statistics.largest_free_run.max(bin.longest_free_run());
+                }
+            }
+        }
+        statistics
+    }
+
     pub(crate) fn get_metabin(&mut self, hyperion_pointer: &HyperionPointer) -> Option<&Metabin> {
         self.metabins.get(hyperion_pointer.metabin_id() as usize)
     }
@@ -15,16 +15,20 @@ use std::sync::atomic::{AtomicBool, Ordering};
 
 use libc::{calloc,
            free,
+           madvise,
            malloc,
            memcpy,
            memset,
            mmap,
+           mprotect,
            munmap,
            sysconf,
+           MADV_DONTNEED,
            MAP_ANON,
            MAP_FAILED,
            MAP_NORESERVE,
            MAP_PRIVATE,
+           PROT_NONE,
            PROT_READ,
            PROT_WRITE,
            _SC_PAGESIZE};
@@ -116,6 +120,34 @@ pub(crate) unsafe fn auto_allocate_memory(ptr: &mut AtomicMemoryPointer, size: u
     Mmap
 }
 
+/// Set while every `mmap`'ed region is followed by an inaccessible guard page. Enabled by default in debug builds.
+static GUARD_PAGES: AtomicBool = AtomicBool::new(cfg!(debug_assertions));
+/// Set by the first `mmap` allocation, after which `GUARD_PAGES` must not change anymore.
+static GUARD_PAGES_LATCHED: AtomicBool = AtomicBool::new(false);
+
+/// Enables or disables guard pages behind `mmap`'ed regions, so that out-of-bounds writes from offset bugs fault immediately
+/// instead of corrupting the neighboring bin.
+///
+/// Regions must be unmapped with the same layout they were mapped with, so the setting is fixed by the first `mmap` allocation.
+/// Returns `true`, if the setting was applied.
+/// Returns `false`, if memory was already mapped.
+pub fn set_guard_pages(enabled: bool) -> bool {
+    if GUARD_PAGES_LATCHED.load(Ordering::SeqCst) {
+        return false;
+    }
+    GUARD_PAGES.store(enabled, Ordering::SeqCst);
+    true
+}
+
+/// Returns the offset of the guard page behind a `mmap`'ed region of `size` bytes, or `None`, if guard pages are disabled.
+fn guard_page_offset(size: usize) -> Option<usize> {
+    if !GUARD_PAGES.load(Ordering::SeqCst) {
+        return None;
+    }
+    let page_size: usize = unsafe { sysconf(_SC_PAGESIZE) as usize };
+    Some(size.div_ceil(page_size) * page_size)
+}
+
 /// Allocates a given size via `mmap`.
 ///
 /// Returns a raw pointer to the allocated memory, if successful.
@@ -125,12 +157,22 @@ pub(crate) unsafe fn auto_allocate_memory(ptr: &mut AtomicMemoryPointer, size: u
 /// This function operates directly on the virtual memory. Rust cannot check if
 /// the allocation parameters are valid.
 pub(crate) unsafe fn allocate_mmap(size: usize) -> *mut c_void {
-    let p_new: *mut c_void = mmap(null_mut(), size, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANON | MAP_NORESERVE, -1, 0);
+    GUARD_PAGES_LATCHED.store(true, Ordering::SeqCst);
+    let guard_offset: Option<usize> = guard_page_offset(size);
+    let page_size: usize = sysconf(_SC_PAGESIZE) as usize;
+    let mapped_size: usize = guard_offset.map_or(size, |offset: usize| offset + page_size);
+
+    let p_new: *mut c_void = mmap(null_mut(), mapped_size, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANON | MAP_NORESERVE, -1, 0);
     if p_new == MAP_FAILED {
-        null_mut()
-    } else {
-        p_new
+        return null_mut();
     }
+    if let Some(offset) = guard_offset {
+        if mprotect(p_new.add(offset), page_size, PROT_NONE) != 0 {
+            munmap(p_new, mapped_size);
+            return null_mut();
+        }
+    }
+    p_new
 }
 
 pub(crate) unsafe fn allocate_heap(size: usize) -> *mut c_void {
@@ -163,7 +205,19 @@ pub(crate) unsafe fn auto_free_memory(ptr: *mut c_void, size: usize, allocated_b
 }
 
 pub(crate) unsafe fn free_mmap(ptr: *mut c_void, size: usize) -> bool {
-    let ret: c_int = munmap(ptr, size);
+    let mapped_size: usize = guard_page_offset(size).map_or(size, |offset: usize| offset + sysconf(_SC_PAGESIZE) as usize);
+    let ret: c_int = munmap(ptr, mapped_size);
+    ret == 0
+}
+
+/// Returns the physical pages backing the given `mmap`'ed memory region to the operating system.
+///
+/// The region stays mapped and reads as zero on its next access. `ptr` and `size` must be page aligned.
+///
+/// Returns `true`, if the pages were released.
+/// Returns `false`, otherwise.
+pub(crate) unsafe fn release_mmap_pages(ptr: *mut c_void, size: usize) -> bool {
+    let ret: c_int = madvise(ptr, size, MADV_DONTNEED);
     ret == 0
 }
 
@@ -208,3 +262,37 @@ pub(crate) unsafe fn auto_reallocate_memory(
 // }
 // p_tmp
 // }
+
+#[cfg(test)]
+mod allocator_test {
+    use std::ffi::c_void;
+
+    use libc::{sysconf, _SC_PAGESIZE};
+
+    use crate::memorymanager::internals::allocator::{allocate_mmap, free_mmap, guard_page_offset};
+
+    /// Returns the permissions of the mapping containing `address` as listed in `/proc/self/maps`.
+    fn mapping_permissions(address: usize) -> Option<String> {
+        std::fs::read_to_string("/proc/self/maps").unwrap().lines().find_map(|line: &str| {
+            let (range, rest): (&str, &str) = line.split_once(' ')?;
+            let (start, end): (&str, &str) = range.split_once('-')?;
+            let contains: bool = usize::from_str_radix(start, 16).ok()? <= address && address < usize::from_str_radix(end, 16).ok()?;
+            contains.then(|| rest[..4].to_string())
+        })
+    }
+
+    #[test]
+    fn test_guard_page() {
+        let page_size: usize = unsafe { sysconf(_SC_PAGESIZE) as usize };
+        let size: usize = page_size * 2 + 1;
+        let ptr: *mut c_void = unsafe { allocate_mmap(size) };
+        assert!(!ptr.is_null());
+        assert_eq!(mapping_permissions(ptr as usize + size - 1).as_deref(), Some("rw-p"));
+
+        if let Some(offset) = guard_page_offset(size) {
+            assert_eq!(offset, page_size * 3);
+            assert_eq!(mapping_permissions(ptr as usize + offset).as_deref(), Some("---p"));
+        }
+        assert!(unsafe { free_mmap(ptr, size) });
+    }
+}
@@ -1,5 +1,6 @@
 use std::cmp::PartialEq;
 use std::ffi::c_void;
+use std::time::Instant;
 
 use libc::{memcpy, memset};
 
@@ -26,6 +27,65 @@ pub(crate) const COMPRESSION_ATT_LZ4_0: usize = 9;
 pub(crate) const COMPRESSION_ATT_LZ4_1: usize = 1;
 pub(crate) const COMPRESSION_ATT_ZSTD: usize = 1;
 
+/// Limits the bytes processed by compression passes, shared by all arenas.
+static COMPRESSION_BUDGET: spin::Mutex<TokenBucket> = spin::Mutex::new(TokenBucket::new(0));
+
+/// Token bucket refilled with `rate` bytes per second and holding at most one second worth of tokens.
+///
+/// Passes are charged after they ran, as their cost is only known then. The balance may thus become negative, which delays the next
+/// pass until the debt has been refilled.
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    rate: usize,
+    balance: f64,
+    last_refill: Option<Instant>
+}
+
+impl TokenBucket {
+    /// Creates a full bucket refilled with `rate` bytes per second, `0` disables the limit.
+    pub(crate) const fn new(rate: usize) -> Self {
+        TokenBucket {
+            rate,
+            balance: rate as f64,
+            last_refill: None
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        if let Some(last_refill) = self.last_refill {
+            let elapsed: f64 = now.saturating_duration_since(last_refill).as_secs_f64();
+            self.balance = (self.balance + elapsed * self.rate as f64).min(self.rate as f64);
+        }
+        self.last_refill = Some(now);
+    }
+
+    /// Returns `true`, if the limit is disabled or tokens are left at the given point in time.
+    /// Returns `false`, otherwise.
+    pub(crate) fn try_begin(&mut self, now: Instant) -> bool {
+        if self.rate == 0 {
+            return true;
+        }
+        self.refill(now);
+        self.balance > 0.0
+    }
+
+    /// Charges the bucket with the given number of processed bytes.
+    #[cfg(any(test, feature = "compression"))]
+    pub(crate) fn charge(&mut self, bytes: usize) {
+        if self.rate > 0 {
+            self.balance -= bytes as f64;
+        }
+    }
+}
+
+/// Caps the bytes per second background compression may process across all arenas, `0` removes the cap.
+///
+/// Keeps maintenance from starving foreground operations on small machines. Compression passes requested while the budget is
+/// exhausted are skipped.
+pub fn set_compression_budget(bytes_per_second: usize) {
+    *COMPRESSION_BUDGET.lock() = TokenBucket::new(bytes_per_second);
+}
+
 #[derive(Copy, Clone, Default)]
 pub(crate) struct CompressionSlidingWindow {
     metabin: u16,
@@ -117,7 +177,7 @@ pub(crate) fn perform_bin_deflation(bin: &mut Bin, size: usize) {
     }
 
     for i in 0..BIN_ELEMENTS {
-        if bin.chunk_usage_mask[i / 8] & (1 << (i % 8)) == 0 {
+        if bin.is_chunk_used(i) {
             pdb_copy_chunk(bin, size, i, &mut new_mem, &mut nci_iterator, &mut new_chunk_index);
         }
     }
@@ -125,6 +185,8 @@ pub(crate) fn perform_bin_deflation(bin: &mut Bin, size: usize) {
     unsafe {
         auto_free_memory(bin.chunks.get(), BIN_ELEMENTS * size, bin.header.allocated_by());
     }
+    #[cfg(any(test, feature = "compression"))]
+    COMPRESSION_BUDGET.lock().charge(BIN_ELEMENTS * size);
     bin.chunks.clone_from(&mut new_mem);
     bin.header.set_allocated_by(new_allocation);
     unsafe {
@@ -174,6 +236,9 @@ pub(crate) fn decompress_extended(extended_pointer: *mut ExtendedHyperionPointer
 }
 
 pub(crate) fn compress_arena(arena: &mut ArenaInner) -> bool {
+    if !COMPRESSION_BUDGET.lock().try_begin(Instant::now()) {
+        return false;
+    }
     let compression_strategy: CompressionStrategy = get_compression_strategy();
 
     match compression_strategy {
@@ -182,3 +247,54 @@ pub(crate) fn compress_arena(arena: &mut ArenaInner) -> bool {
         _ => perform_arena_compression(arena, compression_strategy)
     }
 }
+
+#[cfg(test)]
+mod compression_test {
+    use std::time::{Duration, Instant};
+
+    use crate::memorymanager::components::arena::{Arena, ArenaInner};
+    use crate::memorymanager::components::bin::{Bin, BIN_ELEMENTS};
+    use crate::memorymanager::internals::allocator::{auto_allocate_memory, AllocatedBy};
+    use crate::memorymanager::internals::compression::{compress_arena, perform_bin_deflation, set_compression_budget, TokenBucket};
+
+    #[test]
+    fn test_token_bucket() {
+        let start: Instant = Instant::now();
+        let mut unlimited: TokenBucket = TokenBucket::new(0);
+        unlimited.charge(usize::MAX);
+        assert!(unlimited.try_begin(start));
+
+        let mut bucket: TokenBucket = TokenBucket::new(1000);
+        assert!(bucket.try_begin(start));
+        bucket.charge(1500);
+        assert!(!bucket.try_begin(start));
+        assert!(!bucket.try_begin(start + Duration::from_millis(400)));
+        assert!(bucket.try_begin(start + Duration::from_millis(600)));
+
+        bucket.charge(100);
+        assert!(bucket.try_begin(start + Duration::from_secs(60)));
+        bucket.charge(1000);
+        assert!(!bucket.try_begin(start + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_compression_budget() {
+        let mut arena: Arena = Arena::default();
+        let inner: &mut spin::mutex::MutexGuard<ArenaInner> = &mut arena.lock();
+
+        let chunk_size: usize = inner.superbins[2].header.size_of_bin() as usize;
+        let bin: &mut Bin = &mut inner.superbins[2].metabins.get_mut(0).unwrap().bins[0];
+        let allocated_by: AllocatedBy = unsafe { auto_allocate_memory(&mut bin.chunks, chunk_size * BIN_ELEMENTS) };
+        bin.header.set_allocated_by(allocated_by);
+        bin.chunk_usage_mask.fill(u32::MAX);
+        bin.toggle_chunk_usage(0);
+
+        // Deflating a single bin exceeds one second worth of budget, so the next pass is skipped.
+        set_compression_budget(1000);
+        perform_bin_deflation(bin, chunk_size);
+        assert!(!compress_arena(inner));
+        set_compression_budget(0);
+
+        inner.teardown_all_superbins();
+    }
+}
@@ -5,6 +5,8 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use libc::{memcpy, memset, size_t};
 
 use crate::memorymanager::components::arena::ArenaInner;
+#[cfg(feature = "compression")]
+use crate::memorymanager::components::arena::NUM_ARENAS;
 use crate::memorymanager::components::bin::{Bin, BIN_ELEMENTS, BIN_ELEMENTS_DEFLATED};
 use crate::memorymanager::components::metabin::Metabin;
 use crate::memorymanager::components::superbin::{get_sblock_id, Superbin};
@@ -21,6 +23,9 @@ pub const CONTAINER_SPLIT_BITS: usize = 3;
 #[allow(unused)]
 pub const PROBE_COMPRESSION_INTERVAL_INACTIVE: usize = 16777216;
 pub const OVERALLOCATION_CAPACITY: usize = 5120;
+/// Pattern written into freed chunks by debug builds with the `poisoning` feature, so that reads through stale pointers stand out from zeroed
+/// memory.
+pub const FREED_CHUNK_POISON: u8 = 0xDB;
 #[allow(unused)]
 pub static DYN_INCREMENT_SIZE: AtomicUsize = AtomicUsize::new(INCREMENT_SIZE_EXT);
 #[allow(unused)]
@@ -61,6 +66,9 @@ pub fn get_chunk_pointer(arena: &mut ArenaInner, hyperion_pointer: &mut Hyperion
 // ehemals ohm_getpointer
 #[allow(unreachable_code, dead_code, unused_variables)]
 pub fn get_chunk(arena: &mut ArenaInner, hyperion_pointer: &mut HyperionPointer, might_increment: i32, needed_character: u8) -> *mut c_void {
+    #[cfg(all(debug_assertions, feature = "poisoning"))]
+    assert_live_pointer(arena, hyperion_pointer);
+
     if hyperion_pointer.is_extended_pointer() {
         return get_chunk_pointer_from_extended(arena, hyperion_pointer, needed_character);
     }
@@ -82,6 +90,26 @@ pub fn get_chunk(arena: &mut ArenaInner, hyperion_pointer: &mut HyperionPointer,
     data
 }
 
+/// Verifies that the given `HyperionPointer` references an allocated chunk of a live bin.
+///
+/// # Panics
+/// Panics with the ids stored in the pointer, if the bin was torn down or the chunk was freed.
+#[cfg(all(debug_assertions, feature = "poisoning"))]
+fn assert_live_pointer(arena: &mut ArenaInner, hyperion_pointer: &mut HyperionPointer) {
+    let bin: &mut Bin = arena.get_bin_ref(hyperion_pointer);
+    if bin.header.compression_state() != CompressionState::NONE {
+        return;
+    }
+    assert!(
+        !bin.is_empty() && bin.is_chunk_used(hyperion_pointer.chunk_id() as usize),
+        "Access through stale HyperionPointer (superbin: {}, metabin: {}, bin: {}, chunk: {})",
+        hyperion_pointer.superbin_id(),
+        hyperion_pointer.metabin_id(),
+        hyperion_pointer.bin_id(),
+        hyperion_pointer.chunk_id()
+    );
+}
+
 pub fn get_chunk_pointer_from_extended(arena: &mut ArenaInner, hyperion_pointer: &mut HyperionPointer, needed_character: u8) -> *mut c_void {
     let extended_pointer_data: *mut c_void = {
         let bin: &mut Bin = arena.get_bin_ref(hyperion_pointer);
@@ -160,6 +188,11 @@ pub fn get_new_pointer(arena: &mut ArenaInner, size: usize, chained_counter: i32
         extended_pointer.data.store(unsafe { allocate_heap(new_size) });
         // extended_pointer.header.set_alloced_by(unsafe { auto_allocate_memory(&mut extended_pointer.data, new_size) });
         extended_pointer.set_flags(size as i32, (new_size - size) as i16, 0, 0, CompressionState::NONE, 0);
+    } else if cfg!(all(debug_assertions, feature = "poisoning")) {
+        let chunk_size: usize = arena.get_superbin_ref(&mut new_hyperion_pointer).header.size_of_bin() as usize;
+        unsafe {
+            write_bytes(get_chunk_pointer(arena, &mut new_hyperion_pointer) as *mut u8, 0, chunk_size);
+        }
     }
     new_hyperion_pointer
 }
@@ -363,6 +396,15 @@ fn update_superbin(arena: &mut ArenaInner, hyperion_pointer: &mut HyperionPointe
     };
 }
 
+/// Returns the byte freed chunks are overwritten with: `FREED_CHUNK_POISON` in debug builds with the `poisoning` feature, `0` otherwise.
+fn freed_chunk_fill() -> u8 {
+    if cfg!(all(debug_assertions, feature = "poisoning")) {
+        FREED_CHUNK_POISON
+    } else {
+        0
+    }
+}
+
 fn free_chunks_deflated(arena: &mut ArenaInner, hyperion_pointer: &mut HyperionPointer) {
     let bin_size: u16 = arena.get_superbin_ref(hyperion_pointer).header.size_of_bin();
     let bin: &mut Bin = arena.get_bin_ref(hyperion_pointer);
@@ -372,7 +414,7 @@ fn free_chunks_deflated(arena: &mut ArenaInner, hyperion_pointer: &mut HyperionP
         for i in 0..BIN_ELEMENTS_DEFLATED {
             if (*probe) == hyperion_pointer.chunk_id() {
                 let chunk: *mut c_void = bin.chunks.add_get(bin_size as usize * i);
-                write_bytes(chunk as *mut u8, 0, bin_size as usize);
+                write_bytes(chunk as *mut u8, freed_chunk_fill(), bin_size as usize);
                 *probe = 0xFF;
                 arena.get_metabin_ref(hyperion_pointer).free_chunks += 1;
                 break;
@@ -394,7 +436,7 @@ fn free_chunks_normal(arena: &mut ArenaInner, hyperion_pointer: &mut HyperionPoi
     } else {
         let chunk_pointer: *mut c_void = get_chunk_pointer(arena, hyperion_pointer);
         unsafe {
-            write_bytes(chunk_pointer as *mut u8, 0, arena.get_superbin_ref(hyperion_pointer).header.size_of_bin() as usize);
+            write_bytes(chunk_pointer as *mut u8, freed_chunk_fill(), arena.get_superbin_ref(hyperion_pointer).header.size_of_bin() as usize);
         }
     }
 
@@ -431,3 +473,20 @@ pub fn probe_compression_without(arena: &mut ArenaInner) {
         }
     }
 }
+
+#[cfg(all(test, feature = "poisoning"))]
+mod core_test {
+    use crate::memorymanager::api::{get_pointer, malloc, Arena, HyperionPointer};
+
+    #[test]
+    #[should_panic(expected = "Access through stale HyperionPointer")]
+    fn test_stale_pointer_detection() {
+        let mut arena: Arena = Arena::default();
+        let mut hyperion_pointer: HyperionPointer = malloc(&mut arena, 200);
+        assert!(!get_pointer(&mut arena, &mut hyperion_pointer, 1, 0).is_null());
+
+        let chunk_id: usize = hyperion_pointer.chunk_id() as usize;
+        arena.lock().get_bin_ref(&mut hyperion_pointer).toggle_chunk_usage(chunk_id);
+        get_pointer(&mut arena, &mut hyperion_pointer, 1, 0);
+    }
+}
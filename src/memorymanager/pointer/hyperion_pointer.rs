@@ -22,7 +22,7 @@ use std::slice::from_raw_parts;
 
 use bitfield_struct::bitfield;
 
-#[bitfield(u32, order = Msb)]
+#[bitfield(u32, order = Msb, repr = u32, from = u32::from_le, into = u32::to_le)]
 pub struct HyperionPointerHeader {
     /// 6 bit superbin id, ranging from 0 to 63
     #[bits(6)]
@@ -122,6 +122,20 @@ impl HyperionPointer {
         write_header(&raw mut self.header, |hdr: &mut HyperionPointerHeader| hdr.set_chunk_id(value))
     }
 
+    /// Encodes this pointer as the bin id followed by the header in little-endian byte order, independent of the host architecture.
+    pub fn to_le_bytes(&self) -> [u8; 5] {
+        let header: [u8; 4] = read(&raw const self.header).into_bits().to_le_bytes();
+        [self.bin_id(), header[0], header[1], header[2], header[3]]
+    }
+
+    /// Decodes a pointer from the little-endian encoding produced by [`HyperionPointer::to_le_bytes`].
+    pub fn from_le_bytes(bytes: [u8; 5]) -> Self {
+        Self {
+            bin_id: bytes[0],
+            header: HyperionPointerHeader::from_bits(u32::from_le_bytes([bytes[1], bytes[2], bytes[3], bytes[4]]))
+        }
+    }
+
     /// Returns `true`, if the calling `HyperionPointer` is an `ExtendedHyperionPointer`.
     /// Returns `false`, otherwise.
     #[inline(always)]
@@ -205,4 +219,18 @@ mod pointer_tests {
         assert_eq!(hp2.bin_id(), 14);
         assert_eq!(hp2.chunk_id(), 15);
     }
+
+    #[test]
+    fn test_hyperion_pointer_le_bytes() {
+        let hyperion_pointer: HyperionPointer = HyperionPointer::new().with_superbin_id(12).with_metabin_id(13).with_bin_id(14).with_chunk_id(15);
+        // superbin 12 << 26 | metabin 13 << 12 | chunk 15 = 0x3000D00F, stored behind the bin id in little-endian byte order
+        let golden: [u8; 5] = [14, 0x0F, 0xD0, 0x00, 0x30];
+        assert_eq!(hyperion_pointer.to_le_bytes(), golden);
+
+        let decoded: HyperionPointer = HyperionPointer::from_le_bytes(golden);
+        assert_eq!(decoded.superbin_id(), 12);
+        assert_eq!(decoded.metabin_id(), 13);
+        assert_eq!(decoded.bin_id(), 14);
+        assert_eq!(decoded.chunk_id(), 15);
+    }
 }